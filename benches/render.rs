@@ -0,0 +1,61 @@
+//! Compares drawing plain QR codes straight into an image with the SVG round trip used for styled
+//! codes: render the document, parse it back with `usvg` and rasterize it.
+//!
+//! ciphercanvas is a binary crate, so the modules under test are compiled into the benchmark.
+//! Most of their items (and, when built as a test, their unit tests' imports) go unused here.
+
+#![allow(dead_code, unused_imports)]
+
+#[path = "../src/aztec.rs"]
+mod aztec;
+#[path = "../src/error.rs"]
+mod error;
+#[path = "../src/image_ops.rs"]
+mod image_ops;
+#[path = "../src/payload.rs"]
+mod payload;
+#[path = "../src/prompt.rs"]
+mod prompt;
+#[path = "../src/qr_generator.rs"]
+mod qr_generator;
+#[path = "../src/svg.rs"]
+mod svg;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use image_ops::svg_to_rgba_image;
+use payload::Payload;
+use qr_generator::{QrCodeOptions, render_document, render_raster};
+use qrcode::QrCode;
+use std::hint::black_box;
+
+fn options(size: u32) -> QrCodeOptions {
+    QrCodeOptions {
+        payload: Payload::Text {
+            data: "https://github.com/walker84837/ciphercanvas".to_string(),
+        },
+        size,
+        formats: vec!["png".to_string()],
+        ..QrCodeOptions::default()
+    }
+}
+
+fn rasterize(c: &mut Criterion) {
+    let qrcode = QrCode::new("https://github.com/walker84837/ciphercanvas").unwrap();
+    let mut group = c.benchmark_group("rasterize");
+    for size in [256, 512, 2048] {
+        let options = options(size);
+        group.bench_with_input(BenchmarkId::new("svg", size), &options, |b, options| {
+            b.iter(|| {
+                let (document, width) = render_document(&qrcode, options, &options.dark_color);
+                svg_to_rgba_image(black_box(document.as_bytes()), width).unwrap()
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("direct", size), &options, |b, options| {
+            b.iter(|| render_raster(black_box(&qrcode), options, &options.dark_color))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, rasterize);
+criterion_main!(benches);
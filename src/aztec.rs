@@ -0,0 +1,137 @@
+//! Aztec codes, the symbology some transit and ticketing systems scan instead of QR codes.
+//!
+//! Only the encoding differs: the module grid is drawn as an SVG document like a plain QR code
+//! and goes through the same label, frame and output steps.
+
+use crate::{error::Error, qr_generator::ErrorCorrection};
+use log::info;
+use rxing::aztec::encoder::aztec_encoder;
+use std::fmt::Write as _;
+
+/// Margin in modules drawn around an Aztec code. The symbology needs no quiet zone, as its finder
+/// pattern sits in the middle, but a thin one keeps the code clear of its surroundings.
+pub(crate) const QUIET_ZONE: u32 = 2;
+
+/// Let the encoder pick the smallest number of layers the payload fits in.
+const AUTO_LAYERS: i32 = 0;
+
+/// An encoded Aztec code: a square grid of modules, `true` for dark ones.
+#[derive(Debug)]
+pub(crate) struct AztecCode {
+    width: usize,
+    modules: Vec<bool>,
+    layers: u32,
+    compact: bool,
+}
+
+impl AztecCode {
+    /// Encode `data` with at least the share of error correction `ec_level` stands for.
+    pub(crate) fn encode(data: &[u8], ec_level: ErrorCorrection) -> Result<Self, Error> {
+        let code = aztec_encoder::encode_bytes(data, ec_percent(ec_level), AUTO_LAYERS)
+            .map_err(|e| Error::Aztec(format!("Failed to generate the Aztec code: {e}")))?;
+        let width = code.getSize() as usize;
+        let matrix = code.getMatrix();
+        let modules = (0..width)
+            .flat_map(|y| (0..width).map(move |x| (x as u32, y as u32)))
+            .map(|(x, y)| matrix.get(x, y))
+            .collect();
+        info!(
+            "Aztec code generated successfully ({} layers{}).",
+            code.getLayers(),
+            if code.isCompact() { ", compact" } else { "" }
+        );
+        Ok(Self {
+            width,
+            modules,
+            layers: code.getLayers(),
+            compact: code.isCompact(),
+        })
+    }
+
+    /// Number of modules across the code, without the quiet zone.
+    pub(crate) fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Number of data layers around the finder pattern.
+    pub(crate) fn layers(&self) -> u32 {
+        self.layers
+    }
+
+    /// Whether this is a compact code, with a smaller finder pattern and at most four layers.
+    pub(crate) fn compact(&self) -> bool {
+        self.compact
+    }
+
+    /// Number of modules across the rendered image, including the quiet zone on both sides.
+    pub(crate) fn modules_with_quiet_zone(&self) -> u32 {
+        self.width as u32 + 2 * QUIET_ZONE
+    }
+
+    /// Render the code as an SVG document at least `size` pixels wide, with whole pixels per
+    /// module.
+    pub(crate) fn render_svg(&self, size: u32, dark_color: &str, light_color: &str) -> String {
+        let unit = size.div_ceil(self.modules_with_quiet_zone());
+        let size = unit * self.modules_with_quiet_zone();
+        let mut path = String::new();
+        for (index, _) in self.modules.iter().enumerate().filter(|(_, dark)| **dark) {
+            let left = (index % self.width) as u32 * unit + QUIET_ZONE * unit;
+            let top = (index / self.width) as u32 * unit + QUIET_ZONE * unit;
+            let _ = write!(path, "M{left} {top}h{unit}v{unit}H{left}V{top}");
+        }
+        format!(
+            concat!(
+                r#"<?xml version="1.0" standalone="yes"?>"#,
+                r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{size}" height="{size}" viewBox="0 0 {size} {size}" shape-rendering="crispEdges">"#,
+                r#"<rect x="0" y="0" width="{size}" height="{size}" fill="{light}"/>"#,
+                r#"<path fill="{dark}" d="{path}"/>"#,
+                "</svg>"
+            ),
+            size = size,
+            light = light_color,
+            dark = dark_color,
+            path = path,
+        )
+    }
+}
+
+/// Smallest share of error correction codewords, in percent, for `ec_level`. The levels keep
+/// their QR meaning of roughly how much damage the code survives; 23% is the minimum the Aztec
+/// standard recommends.
+pub(crate) fn ec_percent(ec_level: ErrorCorrection) -> u32 {
+    match ec_level {
+        ErrorCorrection::L => 10,
+        ErrorCorrection::M | ErrorCorrection::Auto => 23,
+        ErrorCorrection::Q => 36,
+        ErrorCorrection::H => 50,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stronger_error_correction_grows_the_code() {
+        let data = "https://github.com/walker84837/ciphercanvas ".repeat(4);
+        let data = data.as_bytes();
+        let low = AztecCode::encode(data, ErrorCorrection::L).unwrap();
+        let high = AztecCode::encode(data, ErrorCorrection::H).unwrap();
+        assert!(low.width() < high.width());
+        assert_eq!(low.modules.len(), low.width() * low.width());
+    }
+
+    #[test]
+    fn svg_draws_dark_modules_inside_the_quiet_zone() {
+        let code = AztecCode::encode(b"ticket 42", ErrorCorrection::M).unwrap();
+        assert!(code.compact());
+        let svg = code.render_svg(100, "#000000", "#ffffff");
+        let modules = code.modules_with_quiet_zone();
+        let size = 100u32.div_ceil(modules) * modules;
+        assert!(svg.contains(&format!(r#"viewBox="0 0 {size} {size}""#)));
+        // The bull's-eye center is always dark.
+        let unit = size / modules;
+        let center = (code.width() as u32 / 2 + QUIET_ZONE) * unit;
+        assert!(svg.contains(&format!("M{center} {center}h{unit}")));
+    }
+}
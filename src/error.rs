@@ -6,16 +6,65 @@ use thiserror::Error;
 pub enum Error {
     #[error("QR code generation error: {0}")]
     QrCode(String),
+    #[error("Aztec code generation error: {0}")]
+    Aztec(String),
+    #[error(
+        "Payload doesn't fit in QR version {requested}; the smallest version that holds it is {minimum}"
+    )]
+    PayloadTooLarge { requested: i16, minimum: i16 },
     #[error("Image processing error: {0}")]
     Image(String),
-    #[error("Unsupported image format: {0}")]
-    UnsupportedFormat(String),
+    #[error("Unsupported image format '{format}'. Supported: {supported}")]
+    UnsupportedFormat { format: String, supported: String },
     #[error("File already exists: {0}")]
     FileExists(String),
+    #[error("Could not create output directory: {0}")]
+    CreateDir(String),
+    #[cfg(feature = "clipboard")]
+    #[error("Clipboard error: {0}")]
+    Clipboard(String),
     #[error("Invalid color value: {0}")]
     InvalidColor(String),
+    #[error("Invalid phone number: {0}")]
+    InvalidPhoneNumber(String),
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    #[error("Invalid username: {0}")]
+    InvalidUsername(String),
+    #[error("Invalid size: {0}")]
+    InvalidSize(String),
+    #[error("Invalid Wi-Fi password: {0}")]
+    InvalidPassword(String),
+    #[error("QR code doesn't read back: {0}")]
+    Unreadable(String),
     #[error(transparent)]
     Io(#[from] io::Error),
     #[error(transparent)]
     Anyhow(#[from] anyhow::Error),
 }
+
+impl Error {
+    /// A stable name for the kind of error, used in machine-readable output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::QrCode(_) => "qr_code",
+            Error::Aztec(_) => "aztec",
+            Error::PayloadTooLarge { .. } => "payload_too_large",
+            Error::Image(_) => "image",
+            Error::UnsupportedFormat { .. } => "unsupported_format",
+            Error::FileExists(_) => "file_exists",
+            Error::CreateDir(_) => "create_dir",
+            #[cfg(feature = "clipboard")]
+            Error::Clipboard(_) => "clipboard",
+            Error::InvalidColor(_) => "invalid_color",
+            Error::InvalidPhoneNumber(_) => "invalid_phone_number",
+            Error::InvalidAddress(_) => "invalid_address",
+            Error::InvalidUsername(_) => "invalid_username",
+            Error::InvalidSize(_) => "invalid_size",
+            Error::InvalidPassword(_) => "invalid_password",
+            Error::Unreadable(_) => "unreadable",
+            Error::Io(_) => "io",
+            Error::Anyhow(_) => "other",
+        }
+    }
+}
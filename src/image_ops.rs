@@ -1,113 +1,899 @@
-use crate::error::Error;
-use log::{error, info};
+use crate::{error::Error, prompt, svg};
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use image::{
+    Delay, Frame, ImageFormat, Rgb, RgbImage, Rgba, RgbaImage,
+    codecs::gif::{GifEncoder, Repeat},
+    imageops::{self, FilterType},
+};
+use log::{info, warn};
 use resvg::render;
+use serde::Serialize;
 use std::{
-    fs::File,
-    io::{BufWriter, prelude::*},
-    path::Path,
+    cell::OnceCell,
+    collections::HashMap,
+    fs::{self, File},
+    io::{self, BufWriter, IsTerminal, prelude::*},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, Mutex, Once, OnceLock, PoisonError, RwLock},
 };
-use tiny_skia::{Pixmap, Transform};
+use tiny_skia::{Pixmap, PremultipliedColorU8, Transform};
 use usvg::{Options, Tree, fontdb};
 
-const SUPPORTED_FORMATS: &[&str] = &["svg", "png"];
+const SUPPORTED_FORMATS: &[&str] = &["svg", "png", "gif", "bmp", "tiff", "eps"];
+
+/// Format used when neither `--format` nor the output extension names one.
+pub const DEFAULT_FORMAT: &str = "svg";
+
+/// Output path that makes [`save_image`] write to stdout instead of a file.
+pub const STDOUT_PATH: &str = "-";
+
+/// Font bundled for label text (public domain, see `assets/fonts/LICENSE.txt`).
+const LABEL_FONT: &[u8] = include_bytes!("../assets/fonts/Tuffy.ttf");
+
+/// Font database shared by every render.
+///
+/// The bundled label font is the default for text, so labels render identically on every system.
+/// Fonts given with `--font` are added by [`load_font_file`], and the system's fonts are added
+/// the first time an SVG with text is rendered, as a fallback for characters the bundled font
+/// lacks. The database is built once so that generating many codes doesn't rebuild it per image.
+fn font_registry() -> &'static RwLock<Arc<fontdb::Database>> {
+    static FONT_DB: OnceLock<RwLock<Arc<fontdb::Database>>> = OnceLock::new();
+    FONT_DB.get_or_init(|| {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(LABEL_FONT.to_vec());
+        db.set_sans_serif_family(svg::LABEL_FONT_FAMILY);
+        RwLock::new(Arc::new(db))
+    })
+}
+
+/// The font database to render `contents` with, loading system fonts if it contains text.
+fn font_database(contents: &[u8]) -> Arc<fontdb::Database> {
+    static SYSTEM_FONTS: Once = Once::new();
+    let registry = font_registry();
+    if contents.windows(5).any(|window| window == b"<text") {
+        SYSTEM_FONTS.call_once(|| {
+            let mut db = registry.write().unwrap_or_else(PoisonError::into_inner);
+            Arc::make_mut(&mut db).load_system_fonts();
+            info!("Loaded system fonts; {} font faces available", db.len());
+        });
+    }
+    Arc::clone(&registry.read().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Load the font file at `path` for SVG text and return its family name, for use in a
+/// `font-family` attribute. Loading the same file again reuses the first load.
+pub fn load_font_file(path: &Path) -> Result<String, Error> {
+    static LOADED: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+    let mut loaded = LOADED
+        .get_or_init(Mutex::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    if let Some(family) = loaded.get(path) {
+        return Ok(family.clone());
+    }
+
+    let data = fs::read(path)?;
+    let mut db = font_registry()
+        .write()
+        .unwrap_or_else(PoisonError::into_inner);
+    let db = Arc::make_mut(&mut db);
+    let ids = db.load_font_source(fontdb::Source::Binary(Arc::new(data)));
+    let family = ids
+        .first()
+        .and_then(|&id| db.face(id))
+        .and_then(|face| face.families.first())
+        .map(|(family, _)| family.clone())
+        .ok_or_else(|| Error::Image(format!("{} is not a usable font file", path.display())))?;
+    info!("Loaded font family '{family}' from {}", path.display());
+
+    loaded.insert(path.to_path_buf(), family.clone());
+    Ok(family)
+}
 
-/// Load and render SVG content into a Pixmap of the specified size.
-pub(crate) fn load_svg(contents: &[u8], size: u32) -> Result<Pixmap, Error> {
-    info!("Loading SVG content with size {size}x{size}");
+/// Load and render SVG content into a Pixmap `width` pixels wide, keeping the aspect ratio.
+pub fn svg_to_pixmap(contents: &[u8], width: u32) -> Result<Pixmap, Error> {
+    info!("Loading SVG content with width {width}");
 
     let options = Options::default();
-    let fontdb = fontdb::Database::new();
-    let tree: Tree = Tree::from_data(contents, &options, &fontdb).map_err(|e| {
-        Error::Image(format!(
-            "Failed to create SVG tree from data of size {size}x{size}: {e}"
-        ))
-    })?;
+    let tree: Tree =
+        Tree::from_data(contents, &options, &font_database(contents)).map_err(|e| {
+            Error::Image(format!(
+                "Failed to create SVG tree from data of width {width}: {e}"
+            ))
+        })?;
 
-    let mut pixmap =
-        Pixmap::new(size, size).ok_or(Error::Image("Failed to create a new Pixmap".to_string()))?;
+    let scale = width as f32 / tree.size().width();
+    let height = (tree.size().height() * scale).round() as u32;
+    let mut pixmap = Pixmap::new(width, height)
+        .ok_or(Error::Image("Failed to create a new Pixmap".to_string()))?;
 
-    render(&tree, Transform::default(), &mut pixmap.as_mut());
+    render(
+        &tree,
+        Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
     info!("Rendered SVG to Pixmap");
 
     Ok(pixmap)
 }
 
-/// Save an image to a file. Supports both SVG and PNG output formats.
+/// Render SVG content into an RGBA image `width` pixels wide, for encoders from the `image` crate.
+pub fn svg_to_rgba_image(contents: &[u8], width: u32) -> Result<RgbaImage, Error> {
+    pixmap_to_rgba_image(svg_to_pixmap(contents, width)?)
+}
+
+/// Convert a pixmap's premultiplied pixels to a straight-alpha image. Opaque pixels are the same
+/// either way, so only translucent ones are converted.
+fn pixmap_to_rgba_image(pixmap: Pixmap) -> Result<RgbaImage, Error> {
+    let (width, height) = (pixmap.width(), pixmap.height());
+    let mut pixels = pixmap.take();
+    for pixel in pixels
+        .chunks_exact_mut(4)
+        .filter(|pixel| pixel[3] != u8::MAX)
+    {
+        if let Some(color) = PremultipliedColorU8::from_rgba(pixel[0], pixel[1], pixel[2], pixel[3])
+        {
+            let color = color.demultiply();
+            pixel.copy_from_slice(&[color.red(), color.green(), color.blue(), color.alpha()]);
+        }
+    }
+    RgbaImage::from_raw(width, height, pixels).ok_or(Error::Image(
+        "Pixmap size doesn't match its pixel data".to_string(),
+    ))
+}
+
+/// The `image` crate format for a raster output `format`, or `None` for vector formats.
+pub fn raster_format(format: &str) -> Option<ImageFormat> {
+    match format {
+        "png" => Some(ImageFormat::Png),
+        "gif" => Some(ImageFormat::Gif),
+        "bmp" => Some(ImageFormat::Bmp),
+        "tiff" => Some(ImageFormat::Tiff),
+        _ => None,
+    }
+}
+
+/// A rendered SVG document, ready to be encoded in one or more formats.
+///
+/// Raster formats share a single rasterization, made the first time one of them is encoded.
+pub struct RenderedImage<'a> {
+    svg: &'a str,
+    width: u32,
+    raster: OnceCell<RgbaImage>,
+}
+
+impl<'a> RenderedImage<'a> {
+    /// `svg` is rasterized `width` pixels wide when a raster format needs it.
+    pub fn new(svg: &'a str, width: u32) -> Self {
+        Self {
+            svg,
+            width,
+            raster: OnceCell::new(),
+        }
+    }
+
+    /// Use `raster` for raster formats instead of rasterizing `svg`.
+    pub fn with_raster(svg: &'a str, raster: RgbaImage) -> Self {
+        Self {
+            svg,
+            width: raster.width(),
+            raster: OnceCell::from(raster),
+        }
+    }
+
+    fn raster(&self) -> Result<&RgbaImage, Error> {
+        if let Some(raster) = self.raster.get() {
+            return Ok(raster);
+        }
+        let raster = svg_to_rgba_image(self.svg.as_bytes(), self.width)?;
+        Ok(self.raster.get_or_init(|| raster))
+    }
+}
+
+/// The MIME type of an output `format`.
+fn mime_type(format: &str) -> &'static str {
+    match format {
+        "svg" => "image/svg+xml",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tiff" => "image/tiff",
+        "eps" => "application/postscript",
+        _ => "image/png",
+    }
+}
+
+/// A `data:` URI holding `bytes` of the given output `format`, for inlining into HTML or JSON.
+pub fn data_uri(format: &str, bytes: &[u8]) -> String {
+    format!("data:{};base64,{}", mime_type(format), BASE64.encode(bytes))
+}
+
+/// Encode an RGBA image with the `image` crate's encoder for `format`.
+///
+/// BMP readers disagree on how to treat an alpha channel, so BMP output is flattened onto white
+/// and written as plain 24-bit RGB.
+pub fn encode_rgba(image: &RgbaImage, format: ImageFormat) -> Result<Vec<u8>, Error> {
+    let mut bytes = io::Cursor::new(Vec::new());
+    let result = match format {
+        ImageFormat::Bmp => flatten_onto_white(image).write_to(&mut bytes, format),
+        _ => image.write_to(&mut bytes, format),
+    };
+    result.map_err(|e| Error::Image(format!("Failed to encode {format:?} image: {e}")))?;
+    Ok(bytes.into_inner())
+}
+
+/// Blend every pixel of `image` over opaque white, dropping the alpha channel.
+fn flatten_onto_white(image: &RgbaImage) -> RgbImage {
+    RgbImage::from_fn(image.width(), image.height(), |x, y| {
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let blend = |c: u8| {
+            let (c, a) = (u32::from(c), u32::from(a));
+            ((c * a + 255 * (255 - a) + 127) / 255) as u8
+        };
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Draw `foreground` over the image at `background`, which is scaled and cropped to cover it.
+/// `veil`, usually a translucent light color, is laid over the image first to wash it out.
+pub fn composite_over_background(
+    foreground: &RgbaImage,
+    background: &Path,
+    veil: Rgba<u8>,
+) -> Result<RgbaImage, Error> {
+    let (width, height) = foreground.dimensions();
+    let mut composed = image::open(background)
+        .map_err(|e| Error::Image(format!("Failed to open {}: {e}", background.display())))?
+        .resize_to_fill(width, height, FilterType::Lanczos3)
+        .into_rgba8();
+    if veil[3] > 0 {
+        imageops::overlay(
+            &mut composed,
+            &RgbaImage::from_pixel(width, height, veil),
+            0,
+            0,
+        );
+    }
+    imageops::overlay(&mut composed, foreground, 0, 0);
+    info!("Composited QR code over {}", background.display());
+    Ok(composed)
+}
+
+/// Columns and rows of a sheet of copies, as given to `--tile` in `COLSxROWS` form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileGrid {
+    pub columns: u32,
+    pub rows: u32,
+}
+
+impl FromStr for TileGrid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (columns, rows) = s
+            .split_once(['x', 'X'])
+            .ok_or_else(|| format!("expected COLSxROWS, e.g. 3x4, got {s:?}"))?;
+        let count = |value: &str| match value.trim().parse::<u32>() {
+            Ok(count) if count > 0 => Ok(count),
+            _ => Err(format!("{value:?} isn't a positive number of tiles")),
+        };
+        Ok(Self {
+            columns: count(columns)?,
+            rows: count(rows)?,
+        })
+    }
+}
+
+/// A sheet of identical codes for printing, e.g. sticker sheets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tiling {
+    pub grid: TileGrid,
+    /// Space between neighbouring copies, in pixels.
+    pub gap: u32,
+    /// Draw thin guides to cut along between the copies.
+    pub cut_lines: bool,
+}
+
+/// Color of the guides drawn by [`Tiling::cut_lines`]: light enough not to be mistaken for part of
+/// a code.
+const CUT_LINE_COLOR: Rgba<u8> = Rgba([0xa0, 0xa0, 0xa0, 0xff]);
+
+/// Lay out copies of `image` in a grid on one sheet, filling the gaps with `background`.
+///
+/// Cut lines are one pixel wide and run the full length of the sheet through the middle of each
+/// gap, or along the seam between copies when there is no gap. Fails if the sheet would be too
+/// large to describe.
+pub fn tile_image(
+    image: &RgbaImage,
+    tiling: Tiling,
+    background: Rgba<u8>,
+) -> Result<RgbaImage, Error> {
+    let (width, height) = image.dimensions();
+    let TileGrid { columns, rows } = tiling.grid;
+    let gap = tiling.gap;
+    // Copies plus the gaps between them; every offset below is smaller than this.
+    let length = |count: u32, side: u32| {
+        count
+            .checked_mul(side)?
+            .checked_add((count - 1).checked_mul(gap)?)
+    };
+    let (Some(sheet_width), Some(sheet_height)) = (length(columns, width), length(rows, height))
+    else {
+        return Err(Error::Image(format!(
+            "A {columns}x{rows} sheet of {width}x{height} copies {gap} pixels apart is too large"
+        )));
+    };
+    let mut sheet = RgbaImage::from_pixel(sheet_width, sheet_height, background);
+    for row in 0..rows {
+        for column in 0..columns {
+            let (x, y) = (column * (width + gap), row * (height + gap));
+            imageops::replace(&mut sheet, image, i64::from(x), i64::from(y));
+        }
+    }
+
+    if tiling.cut_lines {
+        for column in 1..columns {
+            let x = column * (width + gap) - gap.div_ceil(2);
+            for y in 0..sheet.height() {
+                sheet.put_pixel(x, y, CUT_LINE_COLOR);
+            }
+        }
+        for row in 1..rows {
+            let y = row * (height + gap) - gap.div_ceil(2);
+            for x in 0..sheet.width() {
+                sheet.put_pixel(x, y, CUT_LINE_COLOR);
+            }
+        }
+    }
+    info!(
+        "Tiled {columns}x{rows} copies onto a {}x{} sheet",
+        sheet.width(),
+        sheet.height()
+    );
+    Ok(sheet)
+}
+
+/// Encode SVG frames into a looping GIF `width` pixels wide, showing each frame for `delay_ms`.
+pub fn encode_gif(frames: &[String], width: u32, delay_ms: u32) -> Result<Vec<u8>, Error> {
+    let gif_error = |e| Error::Image(format!("Failed to encode GIF image: {e}"));
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = GifEncoder::new(&mut bytes);
+        encoder.set_repeat(Repeat::Infinite).map_err(gif_error)?;
+        for frame in frames {
+            let image = svg_to_rgba_image(frame.as_bytes(), width)?;
+            let delay = Delay::from_numer_denom_ms(delay_ms, 1);
+            encoder
+                .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                .map_err(gif_error)?;
+        }
+    }
+    info!("Encoded {} GIF frame(s)", frames.len());
+    Ok(bytes)
+}
+
+/// Decide the output format from the explicit `--format` value and the output path.
+///
+/// An explicit format always wins; a conflicting output extension only produces a warning.
+/// Without an explicit format, a supported output extension selects the format, falling back to
+/// [`DEFAULT_FORMAT`].
+pub fn resolve_format(format: Option<&str>, output: Option<&Path>) -> String {
+    let extension = output
+        .filter(|path| !is_stdout(path))
+        .and_then(format_from_extension);
+
+    match (format, extension) {
+        (Some(format), Some(extension)) => {
+            if !format.eq_ignore_ascii_case(&extension) {
+                warn!(
+                    "Output extension '.{extension}' doesn't match the requested format '{format}'; \
+                     writing {format} data to the given path anyway."
+                );
+            }
+            format.to_lowercase()
+        }
+        (Some(format), None) => format.to_lowercase(),
+        (None, Some(extension)) => {
+            info!("Inferred output format '{extension}' from the output extension");
+            extension
+        }
+        (None, None) => DEFAULT_FORMAT.to_string(),
+    }
+}
+
+/// The supported format named by the extension of `path`, if any.
+pub fn format_from_extension(path: &Path) -> Option<String> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    SUPPORTED_FORMATS
+        .contains(&extension.as_str())
+        .then_some(extension)
+}
+
+/// Whether `path` has the extension of a supported format.
+pub fn has_format_extension(path: &Path) -> bool {
+    format_from_extension(path).is_some()
+}
+
+/// The file actually written for `output` in `format`.
+///
+/// A path that already carries a supported image extension is used verbatim, so the user's
+/// chosen name is never rewritten. Otherwise the format's extension is appended, keeping any
+/// dots already in the name.
+pub fn output_file_path(output: &Path, format: &str) -> PathBuf {
+    if has_format_extension(output) {
+        output.to_path_buf()
+    } else {
+        append_extension(output, format)
+    }
+}
+
+/// `path` with `.extension` added after the full file name, unlike [`Path::with_extension`],
+/// which would replace whatever follows the last dot.
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// Whether `path` is the conventional `-` placeholder for standard output.
+pub fn is_stdout(path: &Path) -> bool {
+    path.as_os_str() == STDOUT_PATH
+}
+
+/// The path for `format` when writing `output` in several formats at once.
 ///
-/// When processing a PNG image, if the requested size is small (<256px), a warning is logged.
+/// A single format writes to `output` as given (see [`output_file_path`]). With several, each
+/// file takes its format's extension so they don't overwrite each other.
+pub fn output_path_for_format(output: &Path, format: &str, multiple: bool) -> PathBuf {
+    if multiple && has_format_extension(output) {
+        output.with_extension(format)
+    } else if multiple {
+        append_extension(output, format)
+    } else {
+        output.to_path_buf()
+    }
+}
+
+/// Where [`save_image`] wrote an image, in which format, and how large it was.
+#[derive(Debug, Serialize)]
+pub struct SavedImage {
+    pub path: PathBuf,
+    pub format: String,
+    pub bytes: usize,
+}
+
+/// Create any missing parent directories of `path`.
+fn create_parent_dirs(path: &Path) -> Result<(), Error> {
+    let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) else {
+        return Ok(());
+    };
+    if parent.is_dir() {
+        return Ok(());
+    }
+
+    info!("Creating output directory {}", parent.display());
+    fs::create_dir_all(parent).map_err(|e| Error::CreateDir(format!("{}: {e}", parent.display())))
+}
+
+/// The error for an unknown `format`, listing the formats that are supported instead.
+fn unsupported_format(format: &str) -> Error {
+    Error::UnsupportedFormat {
+        format: format.to_string(),
+        supported: SUPPORTED_FORMATS.join(", "),
+    }
+}
+
+/// Encode the rendered document into the bytes of the requested output format.
+fn encode_image(format: &str, image: &RenderedImage) -> Result<Vec<u8>, Error> {
+    if format == "svg" {
+        return Ok(image.svg.as_bytes().to_vec());
+    }
+    // EPS is drawn from the QR matrix rather than converted from SVG; see `write_image`.
+    let Some(raster) = raster_format(format) else {
+        return Err(unsupported_format(format));
+    };
+    let size = image.width;
+    if size <= 256 {
+        warn!("Image size is {size}x{size}, which may result in lower quality.");
+    }
+    encode_rgba(image.raster()?, raster)
+}
+
+/// Ask the user whether an existing file may be overwritten. Non-interactive sessions answer no.
+fn confirm_overwrite(path: &Path) -> Result<bool, Error> {
+    let question = format!("File {} exists, overwrite?", path.display());
+    Ok(prompt::confirm(&question)?.unwrap_or(false))
+}
+
+/// How [`save_image`] and [`write_image`] treat the file system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WriteOptions {
+    /// Replace existing files without asking.
+    pub overwrite: bool,
+    /// Ask before replacing an existing file on an interactive terminal.
+    pub prompt_confirm: bool,
+    /// Create missing parent directories of the output path.
+    pub create_dirs: bool,
+    /// Write to the output path exactly as given, without adding an extension.
+    pub exact_path: bool,
+    /// Encode everything and run every check, but don't write anything.
+    pub dry_run: bool,
+    /// Write images sent to stdout as a base64 `data:` URI instead of raw bytes.
+    pub data_uri: bool,
+}
+
+/// Save an image to a file in each of `formats`.
+///
+/// When writing a raster image, if the size is small (<256px), a warning is logged. Raster
+/// formats share one rasterization of the document.
+///
+/// If an output file already exists and `write.overwrite` is not set, the file is left untouched
+/// and [`Error::FileExists`] is returned, unless `write.prompt_confirm` is set and the user agrees
+/// to overwrite it on an interactive terminal.
+///
+/// Missing parent directories of `output` are created first when `write.create_dirs` is set.
 ///
-/// # Usage Examples
+/// An `output` of `-` ([`STDOUT_PATH`]) writes the raw image bytes to stdout instead.
 ///
-/// Save an SVG image:
-/// ```rust
-/// use ciphercanvas::save_image;
-/// let image = "<svg>...</svg>";
-/// let format = "svg";
-/// let size = 128;
-/// let output = PathBuf::from("output.svg");
-/// save_image(&output, &format, &image, size).unwrap();
-/// ```
+/// With `write.dry_run`, nothing is written, but the result is the same as for a real run.
 ///
-/// Save a PNG image:
-/// ```rust
-/// use ciphercanvas::save_image;
-/// let image = "<svg>...</svg>";
-/// let format = "png";
-/// let size = 128;
-/// let output = PathBuf::from("output.png");
-/// save_image(&output, &format, &image, size).unwrap();
-/// ```
+/// Returns the paths that were written, which may differ from `output` when the format's
+/// extension had to be added (see [`output_file_path`] and [`output_path_for_format`]), along
+/// with the number of bytes written to each.
+///
+/// For example, an `output` of `code` with the formats `png` and `svg` writes `code.png` and
+/// `code.svg` from the same [`RenderedImage`], while an `output` of `code.png` with only the `png`
+/// format is written as given.
 pub fn save_image(
     output: &Path,
-    format: &str,
-    image: &str,
-    size: u32,
-    overwrite: bool,
-) -> Result<(), Error> {
-    info!(
-        "Starting to save image with format '{}' to {}",
-        format,
-        output.display()
-    );
+    formats: &[String],
+    image: &RenderedImage,
+    write: WriteOptions,
+) -> Result<Vec<SavedImage>, Error> {
+    let multiple = formats.len() > 1;
+    formats
+        .iter()
+        .map(|format| {
+            info!(
+                "Starting to save image with format '{}' to {}",
+                format,
+                output.display()
+            );
+
+            if !SUPPORTED_FORMATS.contains(&format.as_str()) {
+                return Err(unsupported_format(format));
+            }
 
-    if !SUPPORTED_FORMATS.contains(&format) {
-        return Err(Error::UnsupportedFormat(format.to_string()));
+            let bytes = encode_image(format, image)?;
+            write_image(
+                &output_path_for_format(output, format, multiple),
+                format,
+                &bytes,
+                write,
+            )
+        })
+        .collect()
+}
+
+/// Write already encoded image `bytes` to `output`, following the same overwrite, directory,
+/// stdout and dry-run rules as [`save_image`].
+pub fn write_image(
+    output: &Path,
+    format: &str,
+    bytes: &[u8],
+    write: WriteOptions,
+) -> Result<SavedImage, Error> {
+    if is_stdout(output) {
+        if write.dry_run {
+            info!("Dry run: not writing {format} image to stdout");
+        } else {
+            let mut stdout = io::stdout().lock();
+            if write.data_uri {
+                writeln!(stdout, "{}", data_uri(format, bytes))?;
+            } else {
+                stdout.write_all(bytes)?;
+            }
+            stdout.flush()?;
+            info!("Wrote {format} image to stdout");
+        }
+        return Ok(SavedImage {
+            path: output.to_path_buf(),
+            format: format.to_string(),
+            bytes: bytes.len(),
+        });
     }
 
-    let file_path = output.with_extension(format);
+    let file_path = if write.exact_path {
+        output.to_path_buf()
+    } else {
+        output_file_path(output, format)
+    };
 
-    if file_path.exists() && !overwrite {
+    // A dry run doesn't ask, so it assumes the user would agree if a prompt would be shown.
+    let may_overwrite = |path: &Path| -> Result<bool, Error> {
+        Ok(write.overwrite
+            || (write.prompt_confirm
+                && if write.dry_run {
+                    io::stdin().is_terminal()
+                } else {
+                    confirm_overwrite(path)?
+                }))
+    };
+    if file_path.exists() && !may_overwrite(&file_path)? {
         return Err(Error::FileExists(format!(
             "File already exists: {}. Use --overwrite to force overwrite.",
             file_path.display()
         )));
     }
 
-    match format {
-        "svg" => {
-            let mut writer = BufWriter::new(File::create(&file_path)?);
-            writer.write_all(image.as_bytes())?;
-            info!("Saved SVG image to {}", file_path.display());
-        }
-        "png" => {
-            if size <= 256 {
-                error!("Warning: Image size is {size}x{size}, which may result in lower quality.",);
-            }
-            let pixmap = load_svg(image.as_bytes(), size)?;
-            pixmap.save_png(&file_path).map_err(|e| {
-                Error::Image(format!(
-                    "Failed to save PNG image to {}: {}",
-                    file_path.display(),
-                    e
-                ))
-            })?;
-            info!("Saved PNG image to {}", file_path.display());
-        }
-        _ => {
-            return Err(Error::UnsupportedFormat(format.to_string()));
-        }
+    if write.dry_run {
+        info!("Dry run: not writing {}", file_path.display());
+        return Ok(SavedImage {
+            path: file_path,
+            format: format.to_string(),
+            bytes: bytes.len(),
+        });
     }
 
+    if write.create_dirs {
+        create_parent_dirs(&file_path)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(&file_path)?);
+    writer.write_all(bytes)?;
+    writer.flush()?;
+
     info!("Image saved successfully to {}", file_path.display());
-    Ok(())
+    Ok(SavedImage {
+        path: file_path,
+        format: format.to_string(),
+        bytes: bytes.len(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_inferred_from_extension() {
+        let format = resolve_format(None, Some(Path::new("wifi.png")));
+        assert_eq!(format, "png");
+    }
+
+    #[test]
+    fn explicit_format_wins_over_extension() {
+        let format = resolve_format(Some("svg"), Some(Path::new("wifi.png")));
+        assert_eq!(format, "svg");
+    }
+
+    #[test]
+    fn default_format_without_extension() {
+        assert_eq!(
+            resolve_format(None, Some(Path::new("wifi"))),
+            DEFAULT_FORMAT
+        );
+        assert_eq!(resolve_format(None, Some(Path::new("-"))), DEFAULT_FORMAT);
+        assert_eq!(resolve_format(None, None), DEFAULT_FORMAT);
+    }
+
+    #[test]
+    fn gif_has_one_frame_per_document() {
+        use image::{AnimationDecoder, codecs::gif::GifDecoder};
+
+        let frame = |color: &str| {
+            format!(
+                r#"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="{color}"/></svg>"#
+            )
+        };
+        let bytes = encode_gif(&[frame("#000000"), frame("#cc0000")], 20, 250).unwrap();
+        let frames = GifDecoder::new(bytes.as_slice())
+            .unwrap()
+            .into_frames()
+            .collect_frames()
+            .unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].buffer().dimensions(), (20, 20));
+        assert_eq!(frames[1].delay().numer_denom_ms(), (250, 1));
+        assert_eq!(frames[1].buffer().get_pixel(5, 5).0[..3], [0xcc, 0, 0]);
+    }
+
+    #[test]
+    fn raster_formats_encode_requested_size() {
+        let svg = r##"<svg xmlns="http://www.w3.org/2000/svg" width="10" height="10"><rect width="10" height="10" fill="#000000"/></svg>"##;
+        let image = RenderedImage::new(svg, 16);
+        for format in ["png", "bmp", "tiff"] {
+            let bytes = encode_image(format, &image).unwrap();
+            let decoded =
+                image::load_from_memory_with_format(&bytes, raster_format(format).unwrap())
+                    .unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (16, 16));
+        }
+    }
+
+    #[test]
+    fn background_shows_through_transparent_pixels() {
+        let path = std::env::temp_dir().join("ciphercanvas-background-test.png");
+        RgbaImage::from_pixel(4, 4, image::Rgba([0, 200, 0, 255]))
+            .save(&path)
+            .unwrap();
+        let mut foreground = RgbaImage::new(8, 8);
+        foreground.put_pixel(0, 0, image::Rgba([0, 0, 0, 255]));
+
+        let composed =
+            composite_over_background(&foreground, &path, image::Rgba([0, 0, 0, 0])).unwrap();
+        let veiled =
+            composite_over_background(&foreground, &path, image::Rgba([255, 255, 255, 128]))
+                .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(composed.dimensions(), (8, 8));
+        assert_eq!(composed.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(composed.get_pixel(5, 5).0, [0, 200, 0, 255]);
+        assert_eq!(veiled.get_pixel(0, 0).0, [0, 0, 0, 255]);
+        assert_eq!(veiled.get_pixel(5, 5).0[..3], [128, 227, 128]);
+    }
+
+    #[test]
+    fn data_uris_name_the_format() {
+        assert_eq!(
+            data_uri("png", b"\x89PNG"),
+            "data:image/png;base64,iVBORw=="
+        );
+        assert_eq!(
+            data_uri("svg", b"<svg/>"),
+            "data:image/svg+xml;base64,PHN2Zy8+"
+        );
+    }
+
+    #[test]
+    fn tile_grid_parses_columns_and_rows() {
+        let grid: TileGrid = "3x4".parse().unwrap();
+        assert_eq!((grid.columns, grid.rows), (3, 4));
+        assert!("3".parse::<TileGrid>().is_err());
+        assert!("0x2".parse::<TileGrid>().is_err());
+    }
+
+    #[test]
+    fn tiles_are_separated_by_gaps_and_cut_lines() {
+        let black = Rgba([0, 0, 0, 255]);
+        let white = Rgba([255, 255, 255, 255]);
+        let tiling = Tiling {
+            grid: TileGrid {
+                columns: 3,
+                rows: 2,
+            },
+            gap: 4,
+            cut_lines: true,
+        };
+        let sheet = tile_image(&RgbaImage::from_pixel(10, 10, black), tiling, white).unwrap();
+        assert_eq!(sheet.dimensions(), (3 * 10 + 2 * 4, 2 * 10 + 4));
+        assert_eq!(*sheet.get_pixel(11, 5), white);
+        assert_eq!(*sheet.get_pixel(12, 5), CUT_LINE_COLOR);
+        assert_eq!(*sheet.get_pixel(5, 12), CUT_LINE_COLOR);
+        assert_eq!(*sheet.get_pixel(14, 14), black);
+    }
+
+    #[test]
+    fn oversized_tile_sheets_are_an_error() {
+        let white = Rgba([255, 255, 255, 255]);
+        let tiling = Tiling {
+            grid: TileGrid {
+                columns: u32::MAX,
+                rows: 1,
+            },
+            gap: 0,
+            cut_lines: false,
+        };
+        let image = RgbaImage::from_pixel(10, 10, white);
+        assert!(matches!(
+            tile_image(&image, tiling, white),
+            Err(Error::Image(_))
+        ));
+        let tiling = Tiling {
+            grid: TileGrid {
+                columns: 2,
+                rows: 1,
+            },
+            gap: u32::MAX,
+            ..tiling
+        };
+        assert!(matches!(
+            tile_image(&image, tiling, white),
+            Err(Error::Image(_))
+        ));
+    }
+
+    #[test]
+    fn dry_run_writes_nothing() {
+        let dir = std::env::temp_dir().join("ciphercanvas-dry-run-test");
+        let output = dir.join("wifi.svg");
+        let write = WriteOptions {
+            create_dirs: true,
+            dry_run: true,
+            ..WriteOptions::default()
+        };
+        let saved = write_image(&output, "svg", b"<svg/>", write).unwrap();
+        assert_eq!(saved.path, output);
+        assert_eq!(saved.bytes, 6);
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn unsupported_format_lists_alternatives() {
+        let image = RenderedImage::new(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#, 8);
+        let error = encode_image("jpeg", &image).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unsupported image format 'jpeg'. Supported: svg, png, gif, bmp, tiff, eps"
+        );
+    }
+
+    #[test]
+    fn font_files_report_their_family() {
+        let family = load_font_file(Path::new("assets/fonts/Tuffy.ttf")).unwrap();
+        assert_eq!(family, "Tuffy");
+        assert!(load_font_file(Path::new("Cargo.toml")).is_err());
+    }
+
+    #[test]
+    fn bmp_is_flattened_to_rgb() {
+        let mut image = RgbaImage::from_pixel(2, 1, image::Rgba([204, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 0, 0, 0]));
+
+        let bytes = encode_rgba(&image, ImageFormat::Bmp).unwrap();
+        let decoded = image::load_from_memory_with_format(&bytes, ImageFormat::Bmp).unwrap();
+        assert_eq!(decoded.color(), image::ColorType::Rgb8);
+        let decoded = decoded.to_rgb8();
+        assert_eq!(decoded.get_pixel(0, 0).0, [204, 0, 0]);
+        assert_eq!(decoded.get_pixel(1, 0).0, [255, 255, 255]);
+    }
+
+    #[test]
+    fn output_path_keeps_user_extension() {
+        let path = output_file_path(Path::new("wifi.png"), "svg");
+        assert_eq!(path, PathBuf::from("wifi.png"));
+    }
+
+    #[test]
+    fn multiple_formats_get_their_own_extension() {
+        let path = output_path_for_format(Path::new("wifi.png"), "svg", true);
+        assert_eq!(path, PathBuf::from("wifi.svg"));
+        let path = output_path_for_format(Path::new("wifi.png"), "svg", false);
+        assert_eq!(path, PathBuf::from("wifi.png"));
+    }
+
+    #[test]
+    fn output_path_adds_missing_extension() {
+        let path = output_file_path(Path::new("wifi"), "png");
+        assert_eq!(path, PathBuf::from("wifi.png"));
+    }
+
+    #[test]
+    fn extension_is_appended_to_dotted_names() {
+        let path = output_file_path(Path::new("my.file.name"), "svg");
+        assert_eq!(path, PathBuf::from("my.file.name.svg"));
+        let path = output_file_path(Path::new("out/v1.2/wifi.qr.png"), "png");
+        assert_eq!(path, PathBuf::from("out/v1.2/wifi.qr.png"));
+        let path = output_path_for_format(Path::new("wifi.guest"), "png", true);
+        assert_eq!(path, PathBuf::from("wifi.guest.png"));
+        let path = output_path_for_format(Path::new("wifi.guest.svg"), "png", true);
+        assert_eq!(path, PathBuf::from("wifi.guest.png"));
+    }
+
+    #[test]
+    fn exact_path_is_written_verbatim() {
+        let dir = std::env::temp_dir().join("ciphercanvas-exact-path-test");
+        let output = dir.join("final.image");
+        let write = WriteOptions {
+            exact_path: true,
+            create_dirs: true,
+            ..WriteOptions::default()
+        };
+        let saved = write_image(&output, "svg", b"<svg/>", write).unwrap();
+        assert_eq!(saved.path, output);
+        assert!(output.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
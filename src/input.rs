@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use log::warn;
+use std::{
+    convert::Infallible,
+    fmt,
+    io::{self, IsTerminal},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// A secret given on the command line. Its `Debug` output is redacted so the value never ends up
+/// in logs, such as the parsed arguments printed in verbose mode.
+#[derive(Clone)]
+pub(crate) struct Secret(pub(crate) String);
+
+impl fmt::Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Secret(<redacted>)")
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+/// Where a value supplied on the command line is read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum InputSource {
+    /// A value given directly as an argument.
+    Literal(String),
+    /// The named environment variable.
+    Env(String),
+    /// A file. A single trailing newline is stripped.
+    File(PathBuf),
+    /// Standard input: a prompt with echo disabled on a terminal, otherwise everything piped in.
+    /// A single trailing newline is stripped from piped input.
+    Stdin,
+}
+
+/// Read a value from `source`. A single trailing newline is stripped from files and piped
+/// input.
+pub(crate) fn read_input(source: InputSource) -> Result<String> {
+    read_value(source, true)
+}
+
+/// Read a value from `source`, stripping a single trailing newline from files and piped input
+/// only when `trim` is set.
+fn read_value(source: InputSource, trim: bool) -> Result<String> {
+    let trim = trim && matches!(source, InputSource::File(_) | InputSource::Stdin);
+    let value = read_exact(source)?;
+    Ok(if trim {
+        strip_trailing_newline(&value).to_string()
+    } else {
+        value
+    })
+}
+
+/// Read a value from `source` as it is.
+fn read_exact(source: InputSource) -> Result<String> {
+    match source {
+        InputSource::Literal(value) => Ok(value),
+        InputSource::Env(var) => std::env::var(&var)
+            .with_context(|| format!("Could not read environment variable {var}")),
+        InputSource::File(path) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Could not read file: {}", path.display())),
+        InputSource::Stdin if io::stdin().is_terminal() => {
+            rpassword::read_password().context("Could not read from stdin.")
+        }
+        InputSource::Stdin => {
+            io::read_to_string(io::stdin().lock()).context("Could not read from stdin.")
+        }
+    }
+}
+
+// Helper function to read password from an environment variable, a file, a literal value or
+// stdin, in that order. With `trim`, a single trailing newline is stripped from file and piped
+// passwords.
+pub(crate) fn get_password(
+    password_env: Option<String>,
+    password_file: Option<PathBuf>,
+    password: Option<Secret>,
+    confirm: bool,
+    trim: bool,
+) -> Result<String> {
+    let source = match (password_env, password_file, password) {
+        (Some(var), _, _) => InputSource::Env(var),
+        (None, Some(path), _) => InputSource::File(path),
+        (None, None, Some(value)) => {
+            warn!(
+                "Passing the password with --password may expose it in shell history and process listings."
+            );
+            InputSource::Literal(value.0)
+        }
+        (None, None, None) => InputSource::Stdin,
+    };
+
+    let from_stdin = source == InputSource::Stdin;
+    let password = if from_stdin && confirm && io::stdin().is_terminal() {
+        read_confirmed_password()?
+    } else {
+        read_value(source, trim).context("Could not read the Wi-Fi password")?
+    };
+    // An empty read usually means stdin was closed rather than an empty password.
+    if from_stdin && password.is_empty() {
+        anyhow::bail!("No password provided on stdin. Use --open for a network without one.");
+    }
+    Ok(password)
+}
+
+// Prompt for the password twice on the terminal, asking again until both entries match
+fn read_confirmed_password() -> Result<String> {
+    loop {
+        let password =
+            rpassword::prompt_password("Wi-Fi password: ").context("Could not read password.")?;
+        let confirmation = rpassword::prompt_password("Confirm password: ")
+            .context("Could not read password confirmation.")?;
+        if password == confirmation {
+            return Ok(password);
+        }
+        eprintln!("Passwords do not match, please try again.");
+    }
+}
+
+/// Strip exactly one trailing `\n` or `\r\n`, as left behind by `echo value > file`.
+fn strip_trailing_newline(value: &str) -> &str {
+    value
+        .strip_suffix("\r\n")
+        .or_else(|| value.strip_suffix('\n'))
+        .unwrap_or(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_single_newline() {
+        assert_eq!(strip_trailing_newline("MyNetwork\n"), "MyNetwork");
+        assert_eq!(strip_trailing_newline("MyNetwork\r\n"), "MyNetwork");
+    }
+
+    #[test]
+    fn file_password_keeps_trailing_spaces() {
+        let path = std::env::temp_dir().join("ciphercanvas-password-file-test.txt");
+        std::fs::write(&path, "pass word \n").unwrap();
+        let password = get_password(None, Some(path.clone()), None, false, true).unwrap();
+        let exact = get_password(None, Some(path.clone()), None, false, false).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(password, "pass word ");
+        assert_eq!(exact, "pass word \n");
+    }
+
+    #[test]
+    fn keeps_other_whitespace() {
+        assert_eq!(strip_trailing_newline("My Network \n\n"), "My Network \n");
+        assert_eq!(strip_trailing_newline(" MyNetwork "), " MyNetwork ");
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{Encryption, error::Error, payload, payload::Payload};
+use clap::ValueEnum;
+use log::warn;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// One QR code requested on a line of `stdin-jobs` input, e.g.
+/// `{"type": "tel", "data": "+1 555 123 4567", "output": "call.png", "size": 256}`.
+///
+/// Fields left out fall back to the options given on the command line.
+#[derive(Debug, Deserialize)]
+pub(crate) struct Job {
+    #[serde(flatten)]
+    pub(crate) payload: JobPayload,
+    pub(crate) output: PathBuf,
+    pub(crate) size: Option<u32>,
+    pub(crate) format: Option<String>,
+}
+
+/// What a job encodes, selected by its `type` field.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub(crate) enum JobPayload {
+    /// `data` is encoded as given.
+    Text { data: String },
+    /// `data` is a phone number, normalized like the `tel` subcommand's.
+    Tel { data: String },
+    /// Wi-Fi credentials, as for the `generate` subcommand.
+    Wifi {
+        ssid: String,
+        #[serde(default)]
+        password: String,
+        encryption: Option<String>,
+    },
+}
+
+impl Job {
+    /// Parse one line of NDJSON input.
+    pub(crate) fn parse(line: &str) -> Result<Self, Error> {
+        serde_json::from_str(line).map_err(|e| Error::Anyhow(anyhow::anyhow!("Invalid job: {e}")))
+    }
+}
+
+impl JobPayload {
+    /// Validate the job's data and turn it into the payload to encode.
+    pub(crate) fn into_payload(self) -> Result<Payload, Error> {
+        match self {
+            JobPayload::Text { data } => Ok(Payload::Text { data }),
+            JobPayload::Tel { data } => {
+                let number =
+                    payload::normalize_phone_number(&data).map_err(Error::InvalidPhoneNumber)?;
+                Ok(Payload::Tel { number })
+            }
+            JobPayload::Wifi {
+                ssid,
+                password,
+                encryption,
+            } => {
+                let encryption = match encryption {
+                    Some(name) => Encryption::from_str(&name, true).map_err(|_| {
+                        Error::Anyhow(anyhow::anyhow!(
+                            "Unknown encryption {name:?}; expected wpa, wpa2, wpa3, wep or none"
+                        ))
+                    })?,
+                    None => Encryption::Wpa,
+                };
+                let encryption = encryption.to_string();
+                if let Err(problem) = payload::check_wifi_password(&encryption, &password) {
+                    warn!("Wi-Fi job for {ssid:?}: {problem}; the code may not connect");
+                }
+                Ok(Payload::Wifi {
+                    ssid,
+                    encryption,
+                    password,
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jobs_are_parsed_by_type() {
+        let job = Job::parse(
+            r#"{"type": "tel", "data": "+1 555-123", "output": "call.png", "size": 256}"#,
+        )
+        .unwrap();
+        assert_eq!(job.output, PathBuf::from("call.png"));
+        assert_eq!(job.size, Some(256));
+        assert_eq!(
+            job.payload.into_payload().unwrap().contents(),
+            "tel:+1555123"
+        );
+
+        let job = Job::parse(
+            r#"{"type": "wifi", "ssid": "Guest", "encryption": "none", "output": "guest.svg"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            job.payload.into_payload().unwrap().contents(),
+            "WIFI:S:Guest;T:NOPASS;;"
+        );
+    }
+
+    #[test]
+    fn wpa_hints_map_to_their_t_values() {
+        for (encryption, expected) in [("wpa2", "T:WPA2;"), ("wpa3", "T:SAE;")] {
+            let line = format!(
+                r#"{{"type": "wifi", "ssid": "Home", "encryption": "{encryption}", "output": "a.png"}}"#
+            );
+            let payload = Job::parse(&line).unwrap().payload.into_payload().unwrap();
+            assert!(payload.contents().contains(expected));
+        }
+    }
+
+    #[test]
+    fn malformed_jobs_are_rejected() {
+        assert!(Job::parse(r#"{"type": "text", "data": "hi"}"#).is_err());
+        assert!(Job::parse(r#"{"type": "fax", "data": "1", "output": "a.png"}"#).is_err());
+        assert!(Job::parse("not json").is_err());
+    }
+}
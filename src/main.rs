@@ -1,44 +1,102 @@
-use anyhow::{Context, Result};
-use clap::{Parser, Subcommand, ValueEnum};
-use log::info;
-use std::{fmt, path::PathBuf};
+use anyhow::Result;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use log::{error, info, warn};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufWriter, IsTerminal, Write},
+    path::PathBuf,
+};
 
+mod aztec;
 mod error;
 mod image_ops;
+mod input;
+mod jobs;
+mod output;
+mod payload;
+mod preview;
+mod prompt;
 mod qr_generator;
+mod svg;
+mod terminal;
 
-use qr_generator::QrCodeOptions;
+use image_ops::{TileGrid, Tiling, WriteOptions};
+use input::{InputSource, Secret, get_password, read_input};
+use jobs::Job;
+use output::{LogFormat, Output};
+use payload::{Coin, Payload};
+use qr_generator::{ErrorCorrection, EyeStyle, ModuleShape, QrCodeOptions, QrMetadata, Symbology};
+use terminal::TerminalMode;
 
-/// Validate a hex color string (e.g. "#000000" or "#ffffff").
-fn validate_hex_color(color: &str) -> Result<(), String> {
-    if !color.starts_with('#') {
-        return Err(format!("Color must start with '#': {color}"));
-    }
-    let hex = &color[1..];
-    if hex.len() != 6 {
-        return Err(format!(
-            "Color must be 6 hex digits after '#': {color} (got {} digits)",
-            hex.len()
-        ));
-    }
-    if u32::from_str_radix(hex, 16).is_err() {
-        return Err(format!("Color contains invalid hex digits: {color}"));
+/// Border width used by `--frame` when `--frame-width` isn't given.
+const DEFAULT_FRAME_WIDTH: u32 = 16;
+
+/// Foreground colors cycled through by `--animate` when `--animate-colors` isn't given. All are
+/// dark enough to scan against a white background.
+const DEFAULT_ANIMATION_COLORS: [&str; 4] = ["#000000", "#1a237e", "#b71c1c", "#1b5e20"];
+
+/// Convert a physical edge length in inches to a pixel count at the given DPI.
+fn physical_to_pixels(inches: f64, dpi: u32) -> Result<u32, error::Error> {
+    let pixels = (inches * f64::from(dpi)).round();
+    if !inches.is_finite() || pixels < 1.0 || pixels > f64::from(u32::MAX) {
+        return Err(error::Error::InvalidSize(format!(
+            "--physical-size {inches} at {dpi} DPI doesn't give a usable pixel size"
+        )));
     }
-    Ok(())
+    info!("Physical size {inches}in at {dpi} DPI is {pixels}px");
+    Ok(pixels as u32)
 }
 
-/// Helper: generate QR code to file, or display in terminal (if kitty_graphics feature enabled and no output path).
-#[cfg(feature = "kitty_graphics")]
-fn generate_or_display_qr(options: &QrCodeOptions) -> Result<(), error::Error> {
-    if options.output_path.is_none() {
-        return qr_generator::print_qr_code_kitty(options);
+/// Helper: generate QR code to file, copy it to the clipboard, or display it in the terminal when
+/// no output path is given. With `preview_terminal`, a saved code is also shown in the terminal.
+fn generate_or_display_qr(
+    options: &QrCodeOptions,
+    terminal: TerminalMode,
+    preview_terminal: Option<TerminalMode>,
+    force_kitty: bool,
+    cells: Option<u32>,
+    clipboard: bool,
+) -> Result<QrMetadata, error::Error> {
+    if clipboard {
+        #[cfg(feature = "clipboard")]
+        return qr_generator::copy_qr_code_to_clipboard(options);
+        #[cfg(not(feature = "clipboard"))]
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "This build has no clipboard support; rebuild with `--features clipboard`"
+        )));
+    }
+
+    if options.output_path.is_some() {
+        let metadata = qr_generator::generate_qr_code(options)?;
+        if let Some(mode) = preview_terminal {
+            print_to_terminal(options, mode, force_kitty, cells)?;
+        }
+        return Ok(metadata);
     }
-    qr_generator::generate_qr_code(options)
+
+    print_to_terminal(options, terminal, force_kitty, cells)
 }
 
-#[cfg(not(feature = "kitty_graphics"))]
-fn generate_or_display_qr(options: &QrCodeOptions) -> Result<(), error::Error> {
-    qr_generator::generate_qr_code(options)
+/// Show the QR code in the terminal with `mode`, resolving [`TerminalMode::Auto`] first. `cells`
+/// only applies to the Kitty graphics protocol.
+#[cfg_attr(not(feature = "kitty_graphics"), allow(unused_variables))]
+fn print_to_terminal(
+    options: &QrCodeOptions,
+    mode: TerminalMode,
+    force_kitty: bool,
+    cells: Option<u32>,
+) -> Result<QrMetadata, error::Error> {
+    match mode.resolve(force_kitty) {
+        #[cfg(feature = "kitty_graphics")]
+        TerminalMode::Kitty => qr_generator::print_qr_code_kitty(options, cells),
+        #[cfg(feature = "iterm2_graphics")]
+        TerminalMode::Iterm2 => qr_generator::print_qr_code_iterm2(options),
+        TerminalMode::Unicode => qr_generator::print_qr_code_unicode(options),
+        TerminalMode::Ascii => qr_generator::print_qr_code_ascii(options),
+        TerminalMode::Auto => unreachable!("TerminalMode::resolve never returns Auto"),
+    }
 }
 
 /// Mature and modular CLI tool to generate QR codes.
@@ -54,6 +112,24 @@ struct CliArgs {
     #[arg(short, long)]
     verbose: bool,
 
+    /// Suppress everything but errors
+    #[arg(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// Machine mode for embedding ciphercanvas in other programs. Nothing is logged; on success
+    /// the only output is the --json object on stdout, and on failure a single JSON object is
+    /// written to stderr.
+    ///
+    /// The error object is {"error": {"kind": "<name>", "message": "<text>"}}, where kind is a
+    /// stable name such as "invalid_color", "file_exists" or "usage". Exit codes: 0 on
+    /// success, 1 when generation fails, 2 for invalid arguments.
+    #[arg(long, conflicts_with_all = ["verbose", "quiet"])]
+    silent_on_success: bool,
+
+    /// How log lines are written to stderr: plain "text", or "json" with one object per line.
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    log_format: LogFormat,
+
     /// Specify subcommand to execute.
     #[command(subcommand)]
     command: Option<Commands>,
@@ -64,52 +140,671 @@ struct CliArgs {
 enum Commands {
     /// Generate a QR code image from Wi-Fi credentials.
     #[command(
-        after_help = "Examples:\n  ciphercanvas generate --ssid MyWifi --password-file ./wifi_pass.txt --output wifi_qr.png\n  ciphercanvas generate --ssid MyGuestWifi --encryption None --output guest_qr.svg\n  echo \"mysecretpassword\" | ciphercanvas generate --ssid MySecureWifi --output secure_qr.png\n  ciphercanvas generate --ssid MyHomeWifi --output home_qr.png (will prompt for password)"
+        after_help = "Examples:\n  ciphercanvas generate --ssid MyWifi --password-file ./wifi_pass.txt --output wifi_qr.png\n  ciphercanvas generate --ssid MyGuestWifi --open --output guest_qr.svg\n  echo \"mysecretpassword\" | ciphercanvas generate --ssid MySecureWifi --output secure_qr.png\n  ciphercanvas generate --ssid MyHomeWifi --output home_qr.png (will prompt for password)"
     )]
     Generate {
-        /// The Wi-Fi network's SSID (name)
-        #[arg(short, long)]
-        ssid: String,
+        #[command(flatten)]
+        wifi: WifiArgs,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Check that a Wi-Fi QR code scans: render it in memory with the given options, decode it
+    /// back and compare the result with the intended payload. Nothing is written; the exit code
+    /// is nonzero when they differ.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas verify --ssid MyWifi --password-file ./wifi_pass.txt --module-shape dots\n  ciphercanvas verify --ssid MyGuestWifi --open --background-image poster.jpg --format png"
+    )]
+    Verify {
+        #[command(flatten)]
+        wifi: WifiArgs,
 
-        /// The encryption type used (WPA, WEP, or None).
-        #[arg(short, long, default_value = "wpa")]
-        encryption: Encryption,
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a QR code from arbitrary text, or from the contents of a file.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas text --data \"https://example.com\" --output link.png\n  ciphercanvas text --file notes.txt --output notes.svg\n  ciphercanvas text --file key.bin --binary --output key.png"
+    )]
+    Text {
+        /// The text to encode.
+        #[arg(short, long, required_unless_present = "file")]
+        data: Option<String>,
 
-        /// The output file to export the QR code image.
+        /// Read the text to encode from the specified file. A single trailing newline is
+        /// stripped.
+        #[arg(long, conflicts_with = "data")]
+        file: Option<PathBuf>,
+
+        /// Encode the bytes of --file verbatim instead of reading it as UTF-8 text, e.g. for
+        /// small binary blobs. Nothing is stripped.
+        #[arg(long, requires = "file", conflicts_with = "data")]
+        binary: bool,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a QR code that starts a phone call when scanned.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas tel --number \"+1 555-123-4567\" --output call.png"
+    )]
+    Tel {
+        /// The phone number to call. Spaces, dashes, dots and parentheses are removed; keep a
+        /// leading "+" for international numbers.
         #[arg(short, long)]
-        output: Option<PathBuf>,
+        number: String,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a cryptocurrency payment request that opens a wallet when scanned.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas crypto --coin btc --address bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq --amount 0.005 --output pay.png"
+    )]
+    Crypto {
+        /// The cryptocurrency to request.
+        #[arg(long)]
+        coin: Coin,
+
+        /// The receiving address. Its prefix, length and characters are checked against --coin.
+        #[arg(long)]
+        address: String,
 
-        /// Read the Wi-Fi network's password from the specified file.
-        /// If not provided, the password will be read from stdin.
+        /// The amount to request, in whole coins (e.g., 0.005).
         #[arg(long)]
-        password_file: Option<PathBuf>,
+        amount: Option<String>,
 
-        /// The size of the QR code image (e.g., 512).
-        #[arg(long, default_value_t = 512)]
-        size: u32,
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a QR code that opens a WhatsApp chat with a phone number.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas whatsapp --number \"+1 555-123-4567\" --message \"Hi, I'd like to book a table\" --output chat.png"
+    )]
+    Whatsapp {
+        /// The phone number to chat with, including the country code. Spaces, dashes, dots and
+        /// parentheses are removed.
+        #[arg(short, long)]
+        number: String,
 
-        /// The output format of the image (e.g., "svg", "png").
-        #[arg(long, default_value = "svg")]
-        format: String,
+        /// Text to prefill in the chat.
+        #[arg(short, long)]
+        message: Option<String>,
 
-        /// The foreground color of the QR code (e.g., "#000000").
-        #[arg(long, default_value = "#000000")]
-        foreground: String,
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a QR code that opens a Telegram profile.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas telegram --username @ciphercanvas --output profile.png"
+    )]
+    Telegram {
+        /// The Telegram username, with or without a leading "@".
+        #[arg(short, long)]
+        username: String,
+
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Generate a QR code from data piped in on stdin, read as the payload --type names.
+    #[command(
+        after_help = "With --type wifi, stdin holds the password and --ssid is still required.\n\nExamples:\n  echo \"https://example.com\" | ciphercanvas encode --output link.png\n  contacts lookup alice --phone | ciphercanvas encode --type tel --output call.png\n  pass show wifi/home | ciphercanvas encode --type wifi --ssid MyHomeWifi --output home.png"
+    )]
+    Encode {
+        /// What the data on stdin is.
+        #[arg(
+            long = "type",
+            visible_alias = "stdin-format",
+            value_name = "TYPE",
+            default_value = "text"
+        )]
+        kind: StdinType,
 
-        /// The background color of the QR code (e.g., "#ffffff")]
-        #[arg(long, default_value = "#ffffff")]
-        background: String,
+        #[command(flatten)]
+        wifi: StdinWifiArgs,
 
-        /// Overwrite existing files without prompt.
-        #[arg(long, default_value_t = false)]
-        overwrite: bool,
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+    /// Print a shell completion script to stdout.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas completions bash > /etc/bash_completion.d/ciphercanvas\n  ciphercanvas completions zsh > ~/.zfunc/_ciphercanvas\n  ciphercanvas completions fish > ~/.config/fish/completions/ciphercanvas.fish"
+    )]
+    Completions {
+        /// The shell to generate completions for.
+        shell: Shell,
     },
+    /// Print the man page, generated from the same definitions as --help.
+    #[command(
+        after_help = "Examples:\n  ciphercanvas man > ciphercanvas.1\n  ciphercanvas man --output /usr/share/man/man1/ciphercanvas.1"
+    )]
+    Man {
+        /// Write the man page to this file instead of stdout.
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Read QR code requests from stdin, one JSON object per line, and generate each as it
+    /// arrives. A failed job is reported and the stream continues.
+    #[command(
+        long_flag = "stdin-jobs",
+        after_help = "Each line is an object with a \"type\" (text, tel or wifi), the data to encode, an \"output\" path and optionally \"size\" and \"format\". Options given on the command line apply to every job.\n\nExamples:\n  echo '{\"type\": \"tel\", \"data\": \"+1 555 123 4567\", \"output\": \"call.png\"}' | ciphercanvas stdin-jobs\n  job-queue | ciphercanvas --stdin-jobs --size 256 --overwrite"
+    )]
+    StdinJobs {
+        #[command(flatten)]
+        render: RenderArgs,
+    },
+}
+
+/// The Wi-Fi network to encode, shared by `generate` and `verify`.
+#[derive(Debug, Args)]
+struct WifiArgs {
+    /// The Wi-Fi network's SSID (name)
+    #[arg(short, long, required_unless_present = "ssid_file")]
+    ssid: Option<String>,
+
+    /// Read the Wi-Fi network's SSID from the specified file.
+    #[arg(long, conflicts_with = "ssid")]
+    ssid_file: Option<PathBuf>,
+
+    /// The encryption type used (WPA, WPA2, WPA3, WEP, or None). WPA covers WPA2 and WPA3
+    /// networks too and is the most widely understood.
+    #[arg(short, long, default_value = "wpa")]
+    encryption: Encryption,
+
+    /// The network is open, e.g. a guest network: implies "--encryption None" and no password
+    /// is read.
+    #[arg(
+        long,
+        visible_alias = "no-password",
+        conflicts_with_all = ["encryption", "password_file", "password_env", "password"]
+    )]
+    open: bool,
+
+    /// Read the Wi-Fi network's password from the specified file.
+    /// If not provided, the password will be read from stdin: with a hidden prompt on a
+    /// terminal, otherwise everything piped in.
+    #[arg(long)]
+    password_file: Option<PathBuf>,
+
+    /// Read the Wi-Fi network's password from the named environment variable.
+    /// Takes precedence over --password-file and stdin.
+    #[arg(long, value_name = "VAR")]
+    password_env: Option<String>,
+
+    /// The Wi-Fi network's password, given directly. It may end up in shell history and
+    /// process listings, so prefer the other sources outside of quick tests.
+    #[arg(long, conflicts_with_all = ["password_file", "password_env"])]
+    password: Option<Secret>,
+
+    /// Read the password only once when typing it interactively, without a confirmation prompt.
+    #[arg(long, default_value_t = false)]
+    no_confirm: bool,
+
+    /// Strip a single trailing newline ("\n" or "\r\n") from a password read from a file or
+    /// piped in, such as the one `echo` adds. This is the default.
+    #[arg(long, overrides_with = "no_trim_password")]
+    trim_password: bool,
+
+    /// Use a password read from a file or piped in exactly as it is, trailing newline included.
+    #[arg(long, default_value_t = false)]
+    no_trim_password: bool,
+
+    /// Fail instead of warning when the password's length doesn't suit the encryption type,
+    /// e.g. a WPA passphrase shorter than 8 characters.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+}
+
+impl WifiArgs {
+    /// Read the SSID and password from wherever they were given and check the password against
+    /// the encryption type.
+    fn into_payload(self) -> Result<Payload, error::Error> {
+        let WifiArgs {
+            ssid,
+            ssid_file,
+            encryption,
+            open,
+            password_file,
+            password_env,
+            password,
+            no_confirm,
+            trim_password: _,
+            no_trim_password,
+            strict,
+        } = self;
+
+        let ssid = match (ssid, ssid_file) {
+            (Some(ssid), _) => ssid,
+            (None, Some(path)) => {
+                read_input(InputSource::File(path)).map_err(error::Error::Anyhow)?
+            }
+            (None, None) => unreachable!("clap requires --ssid or --ssid-file"),
+        };
+
+        let encryption = if open { Encryption::None } else { encryption };
+        // Open networks have no password to prompt for, unless one was given explicitly.
+        let has_password_source =
+            password_env.is_some() || password_file.is_some() || password.is_some();
+        let password = if encryption == Encryption::None && !has_password_source {
+            String::new()
+        } else {
+            get_password(
+                password_env,
+                password_file,
+                password,
+                !no_confirm,
+                !no_trim_password,
+            )
+            .map_err(error::Error::Anyhow)?
+        };
+
+        let encryption = encryption.to_string();
+        if let Err(problem) = payload::check_wifi_password(&encryption, &password) {
+            if strict {
+                return Err(error::Error::InvalidPassword(problem));
+            }
+            warn!("{problem}; the code may not connect");
+        }
+
+        Ok(Payload::Wifi {
+            ssid,
+            encryption,
+            password,
+        })
+    }
+}
+
+/// The Wi-Fi options of `encode --type wifi`, whose password is piped in. They mean the same as
+/// in [`WifiArgs`].
+#[derive(Debug, Args)]
+struct StdinWifiArgs {
+    /// The Wi-Fi network's SSID (name), for --type wifi.
+    #[arg(short, long, required_if_eq("kind", "wifi"))]
+    ssid: Option<String>,
+
+    /// The encryption type used, for --type wifi (WPA, WPA2, WPA3, WEP, or None). Defaults to WPA.
+    #[arg(short, long)]
+    encryption: Option<Encryption>,
+
+    /// Strip a single trailing newline ("\n" or "\r\n") from the piped password. This is the
+    /// default.
+    #[arg(long, overrides_with = "no_trim_password")]
+    trim_password: bool,
+
+    /// Use the piped password exactly as it is, trailing newline included.
+    #[arg(long, default_value_t = false)]
+    no_trim_password: bool,
+
+    /// Fail instead of warning when the password's length doesn't suit the encryption type.
+    #[arg(long, default_value_t = false)]
+    strict: bool,
+}
+
+impl StdinWifiArgs {
+    /// The first of these options given on the command line, which only other types reject.
+    fn first_given(&self) -> Option<&'static str> {
+        let given = [
+            (self.ssid.is_some(), "--ssid"),
+            (self.encryption.is_some(), "--encryption"),
+            (self.trim_password, "--trim-password"),
+            (self.no_trim_password, "--no-trim-password"),
+            (self.strict, "--strict"),
+        ];
+        given
+            .into_iter()
+            .find_map(|(given, option)| given.then_some(option))
+    }
+
+    /// The equivalent `generate` options, reading the password from stdin.
+    fn into_wifi_args(self) -> WifiArgs {
+        WifiArgs {
+            ssid: Some(self.ssid.expect("clap requires --ssid with --type wifi")),
+            ssid_file: None,
+            encryption: self.encryption.unwrap_or(Encryption::Wpa),
+            open: false,
+            password_file: None,
+            password_env: None,
+            password: None,
+            no_confirm: true,
+            trim_password: self.trim_password,
+            no_trim_password: self.no_trim_password,
+            strict: self.strict,
+        }
+    }
+}
+
+/// Options shared by every subcommand that renders a QR code.
+#[derive(Debug, Clone, Args)]
+struct RenderArgs {
+    /// The output file to export the QR code image. Use "-" to write the image to stdout.
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also write the QR code to this file, in the format named by its extension. Repeat it to
+    /// write several files from one generation, e.g. an SVG and a PNG of the same code.
+    #[arg(long, value_name = "PATH", requires = "output")]
+    also_output: Vec<PathBuf>,
+
+    /// Copy the QR code to the system clipboard instead of writing a file: an image for raster
+    /// formats (PNG unless --format says otherwise), or the SVG text for "svg".
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    clipboard: bool,
+
+    /// Print the QR code to stdout as a base64 "data:" URI (e.g. "data:image/png;base64,...")
+    /// instead of writing a file, for inlining into HTML or JSON. PNG unless --format says
+    /// otherwise.
+    #[arg(
+        long,
+        default_value_t = false,
+        conflicts_with_all = ["output", "clipboard", "json"]
+    )]
+    data_uri: bool,
+
+    /// How to show the QR code in the terminal when no output file is given. "auto" picks
+    /// Kitty, iTerm2, Unicode blocks or ASCII depending on what the terminal supports.
+    #[arg(
+        long,
+        value_name = "MODE",
+        default_value = "auto",
+        conflicts_with = "output"
+    )]
+    terminal: TerminalMode,
+
+    /// After saving, also show the code in the terminal, as without --output. MODE works like
+    /// --terminal and defaults to "auto".
+    #[arg(
+        long,
+        value_name = "MODE",
+        num_args = 0..=1,
+        default_missing_value = "auto",
+        requires = "output",
+        conflicts_with = "json"
+    )]
+    preview_terminal: Option<TerminalMode>,
+
+    /// Use the Kitty graphics protocol even when the terminal doesn't appear to support it, e.g.
+    /// in a multiplexer configured to pass it through. Implies "--terminal kitty".
+    #[arg(long, default_value_t = false, conflicts_with = "output")]
+    force_kitty: bool,
+
+    /// How many terminal columns the code spans with the Kitty graphics protocol; the height
+    /// follows so it stays square. Defaults to the width of the Unicode rendering, shrunk to fit
+    /// the terminal.
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    cells: Option<u32>,
+
+    /// The size of the QR code image (e.g., 512).
+    #[arg(long, default_value_t = 512)]
+    size: u32,
+
+    /// Pixels per QR module, as an alternative to --size. The image size becomes the module
+    /// count (including the quiet zone) times this value, so edges stay crisp.
+    #[arg(
+        long,
+        visible_alias = "module-size",
+        value_name = "PX",
+        conflicts_with = "size"
+    )]
+    scale: Option<u32>,
+
+    /// Round --size up to the next multiple of the module count, so modules cover whole
+    /// pixels and raster output stays sharp.
+    #[arg(long, default_value_t = false, conflicts_with = "scale")]
+    snap: bool,
+
+    /// Print resolution in dots per inch, used together with --physical-size.
+    #[arg(long, requires = "physical_size")]
+    dpi: Option<u32>,
+
+    /// Physical edge length of the printed code in inches. The pixel size becomes
+    /// inches * dpi, and SVG output records the physical size so it prints correctly.
+    #[arg(long, value_name = "INCHES", requires = "dpi", conflicts_with_all = ["size", "scale"])]
+    physical_size: Option<f64>,
+
+    /// Intended printed width in millimeters. Reports the effective DPI and module size, and
+    /// warns when they are too low for print or scanning.
+    #[arg(long, value_name = "MM")]
+    print_width_mm: Option<f64>,
+
+    /// Text to print centered below the QR code, such as the network name.
+    /// Given without text, the network name for Wi-Fi codes or the number or username for
+    /// phone and chat codes is used.
+    #[arg(
+        long,
+        visible_alias = "caption",
+        value_name = "TEXT",
+        num_args = 0..=1,
+        default_missing_value = ""
+    )]
+    label: Option<String>,
+
+    /// Font size of --label in pixels. Defaults to a size proportional to the image.
+    #[arg(
+        long,
+        visible_alias = "caption-font-size",
+        value_name = "PX",
+        requires = "label"
+    )]
+    label_size: Option<u32>,
+
+    /// Font file (TrueType or OpenType) to draw --label with, instead of the bundled font.
+    #[arg(long, value_name = "PATH", requires = "label")]
+    font: Option<PathBuf>,
+
+    /// Draw a solid border around the QR code, outside the quiet zone.
+    #[arg(long, default_value_t = false)]
+    frame: bool,
+
+    /// Width of the border in pixels. Implies --frame unless it is 0.
+    #[arg(long, visible_alias = "border-width", value_name = "PX")]
+    frame_width: Option<u32>,
+
+    /// Color of the border (e.g., "#000000"). Defaults to the foreground color.
+    #[arg(long, visible_alias = "border-color", value_name = "COLOR")]
+    frame_color: Option<String>,
+
+    /// How data modules are drawn. Finder patterns stay square so the code remains easy to
+    /// scan.
+    #[arg(long, value_name = "SHAPE", default_value = "square")]
+    module_shape: ModuleShape,
+
+    /// Corner radius of "rounded" modules, as a fraction of the module size (0 to 0.5).
+    #[arg(long, value_name = "RATIO", default_value_t = 0.25)]
+    module_radius: f64,
+
+    /// Color of the finder patterns ("eyes") in the three corners (e.g., "#cc0000").
+    /// Defaults to the foreground color.
+    #[arg(long, value_name = "COLOR")]
+    eye_color: Option<String>,
+
+    /// Color of the solid center of each finder pattern. Defaults to --eye-color.
+    #[arg(long, value_name = "COLOR")]
+    eye_ball_color: Option<String>,
+
+    /// Shape of the finder patterns: "square", "rounded" or "circle". They keep their size and
+    /// position, so the code still scans.
+    #[arg(long, value_name = "STYLE", default_value = "square")]
+    eye_style: EyeStyle,
+
+    /// Fill the data modules with a linear gradient between two comma-separated colors
+    /// (e.g., "#0044cc,#cc0044") instead of the foreground color.
+    #[arg(long, value_name = "FROM,TO", value_delimiter = ',')]
+    gradient: Vec<String>,
+
+    /// Direction of --gradient in degrees. 0 runs left to right, 90 top to bottom.
+    #[arg(
+        long,
+        value_name = "DEGREES",
+        default_value_t = 0.0,
+        requires = "gradient"
+    )]
+    gradient_angle: f64,
+
+    /// Apply --gradient to the finder patterns too. They stay solid by default because
+    /// gradients there can make the code harder to scan.
+    #[arg(long, default_value_t = false, requires = "gradient")]
+    gradient_eyes: bool,
+
+    /// Draw the QR code over this image, scaled and cropped to fill the output. Light modules
+    /// become transparent so the image shows through. Raster formats only; busy or dark
+    /// images can make the code unscannable.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["animate", "animate_colors"])]
+    background_image: Option<PathBuf>,
+
+    /// Wash out the background image with the background color at this opacity (0 to 1), so
+    /// the dark modules stand out. 0 leaves the image as it is.
+    #[arg(
+        long,
+        value_name = "OPACITY",
+        default_value_t = 0.0,
+        requires = "background_image"
+    )]
+    background_opacity: f64,
+
+    /// Lay out copies of the code in a grid on one sheet, e.g. "3x4" for a sticker sheet of three
+    /// columns and four rows. Raster formats only.
+    #[arg(
+        long,
+        value_name = "COLSxROWS",
+        requires = "output",
+        conflicts_with_all = ["animate", "animate_colors"]
+    )]
+    tile: Option<TileGrid>,
+
+    /// Space between tiled copies, in pixels. Each copy keeps its quiet zone, so no gap is needed
+    /// for scanning.
+    #[arg(long, value_name = "PX", default_value_t = 0, requires = "tile")]
+    tile_gap: u32,
+
+    /// Draw thin grey guides to cut along between tiled copies.
+    #[arg(long, default_value_t = false, requires = "tile")]
+    cut_lines: bool,
+
+    /// Write an animated GIF whose foreground cycles through --animate-colors, or through a
+    /// built-in palette of dark colors. The format defaults to "gif", and other formats are
+    /// refused.
+    #[arg(long, default_value_t = false)]
+    animate: bool,
+
+    /// Comma-separated foreground colors to cycle through in an animated GIF, one per frame
+    /// (e.g., "#000000,#cc0000,#0000cc"). The background and quiet zone stay fixed. Implies
+    /// --animate.
+    #[arg(long, value_name = "COLORS", value_delimiter = ',')]
+    animate_colors: Vec<String>,
+
+    /// How long each animation frame is shown, in milliseconds.
+    #[arg(long, value_name = "MS", default_value_t = 500)]
+    frame_delay: u32,
+
+    /// The kind of code to generate. Aztec codes are written to --output only, and take the
+    /// colors, size, label and frame options but none of the QR styling.
+    #[arg(long, value_name = "SYMBOLOGY", default_value = "qr")]
+    symbology: Symbology,
+
+    /// Use a more compact Micro QR code when the payload fits in one, falling back to a
+    /// regular QR code otherwise.
+    #[arg(long, default_value_t = false)]
+    micro: bool,
+
+    /// Force a specific QR version (1-40) instead of the smallest one that fits, so a series
+    /// of codes shares the same module grid. Fails if the payload doesn't fit.
+    #[arg(
+        long = "version",
+        value_name = "1-40",
+        value_parser = clap::value_parser!(i16).range(1..=40),
+        conflicts_with = "micro"
+    )]
+    qr_version: Option<i16>,
+
+    /// Error correction level: l, m, q or h (the default), recovering about 7%, 15%, 25% or 30%
+    /// of the code. "auto" picks the smallest code the payload fits in, then the strongest level
+    /// that keeps it that size.
+    #[arg(long, value_name = "LEVEL", default_value = "h")]
+    ec_level: ErrorCorrection,
+
+    /// The output format of the image (e.g., "svg", "png").
+    /// Defaults to the output file's extension, or "svg" if it has none. Give several
+    /// comma-separated formats (e.g., "png,svg") to write one file per format.
+    #[arg(long, value_delimiter = ',')]
+    format: Vec<String>,
+
+    /// The foreground color of the QR code (e.g., "#000000").
+    #[arg(long, default_value = "#000000")]
+    foreground: String,
+
+    /// The background color of the QR code (e.g., "#ffffff")]
+    #[arg(long, default_value = "#ffffff")]
+    background: String,
+
+    /// Swap the foreground and background colors, e.g. for dark-mode displays.
+    #[arg(long, default_value_t = false)]
+    invert: bool,
+
+    /// Overwrite existing files without prompt.
+    #[arg(long, default_value_t = false)]
+    overwrite: bool,
+
+    /// Fail when the output file exists instead of asking whether to overwrite it. Without an
+    /// interactive terminal, an existing file is always an error.
+    #[arg(long, default_value_t = false, conflicts_with = "overwrite")]
+    no_prompt: bool,
+
+    /// Asking before overwriting is now the default; this flag is kept for existing scripts.
+    #[arg(
+        long,
+        default_value_t = false,
+        hide = true,
+        conflicts_with = "no_prompt"
+    )]
+    prompt_confirm: bool,
+
+    /// Don't create missing parent directories of the output path.
+    #[arg(long, default_value_t = false)]
+    no_mkdir: bool,
+
+    /// Write to the output path exactly as given, even when it has no image extension.
+    #[arg(
+        long,
+        visible_aliases = ["keep-extension", "no-append-extension"],
+        default_value_t = false,
+        requires = "output"
+    )]
+    no_extension: bool,
+
+    /// Run every check and render the code, but don't write any files. Prints the path and
+    /// size of each file that would be written.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "output",
+        conflicts_with = "clipboard"
+    )]
+    dry_run: bool,
+
+    /// Print a JSON object describing the generated QR code to stdout.
+    #[arg(long, default_value_t = false)]
+    json: bool,
+
+    /// Open the written file in the default image viewer. Skipped with a warning on CI and
+    /// machines without a graphical session.
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "output",
+        conflicts_with = "dry_run"
+    )]
+    preview: bool,
 }
 
 /// Valid encryption types for Wi-Fi.
 #[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
 enum Encryption {
+    /// WPA, WPA2 or WPA3 personal; understood by every scanner.
     Wpa,
+    /// Hint that the network uses WPA2. Written as "WPA2", which not every scanner knows.
+    Wpa2,
+    /// Hint that the network uses WPA3 (SAE). Written as "SAE", which not every scanner knows.
+    Wpa3,
     Wep,
     None,
 }
@@ -118,6 +813,8 @@ impl fmt::Display for Encryption {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let encryption_str = match self {
             Encryption::Wpa => "WPA",
+            Encryption::Wpa2 => "WPA2",
+            Encryption::Wpa3 => "SAE",
             Encryption::Wep => "WEP",
             Encryption::None => "nopass",
         };
@@ -125,66 +822,616 @@ impl fmt::Display for Encryption {
     }
 }
 
-// Helper function to read password from file or stdin
-fn get_password(password_file: Option<PathBuf>) -> Result<String> {
-    if let Some(path) = password_file {
-        std::fs::read_to_string(&path)
-            .with_context(|| format!("Could not read password from file: {}", path.display()))
+/// What the data piped into `encode` is, and so which payload it builds.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum StdinType {
+    /// Arbitrary text, e.g. a link, encoded as given.
+    Text,
+    /// A phone number to call, as for the `tel` subcommand.
+    Tel,
+    /// A phone number to open a WhatsApp chat with.
+    Whatsapp,
+    /// A Telegram username.
+    Telegram,
+    /// A Wi-Fi password, for the network named by --ssid.
+    Wifi,
+}
+
+/// Read the data piped in on stdin and build the payload `kind` names from it. A single
+/// trailing newline is stripped. Wi-Fi passwords are read and checked like `generate` does.
+fn read_stdin_payload(kind: StdinType, wifi: StdinWifiArgs) -> Result<Payload, error::Error> {
+    if let Some(option) = wifi.first_given().filter(|_| kind != StdinType::Wifi) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "{option} only applies to --type wifi"
+        )));
+    }
+    if io::stdin().is_terminal() {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "encode reads its data from stdin; pipe it in"
+        )));
+    }
+    if kind == StdinType::Wifi {
+        return wifi.into_wifi_args().into_payload();
+    }
+    let data = read_input(InputSource::Stdin).map_err(error::Error::Anyhow)?;
+    if data.is_empty() {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "No data provided on stdin."
+        )));
+    }
+
+    Ok(match kind {
+        StdinType::Text => Payload::Text { data },
+        StdinType::Tel => Payload::Tel {
+            number: payload::normalize_phone_number(&data)
+                .map_err(error::Error::InvalidPhoneNumber)?,
+        },
+        StdinType::Whatsapp => {
+            let number =
+                payload::normalize_phone_number(&data).map_err(error::Error::InvalidPhoneNumber)?;
+            Payload::WhatsApp {
+                number: number.trim_start_matches('+').to_string(),
+                message: None,
+            }
+        }
+        StdinType::Telegram => Payload::Telegram {
+            username: payload::normalize_telegram_username(&data)
+                .map_err(error::Error::InvalidUsername)?,
+        },
+        StdinType::Wifi => unreachable!("Wi-Fi payloads are read above"),
+    })
+}
+
+/// Validate the rendering options, render `payload` and report where it went.
+fn render_qr_code(payload: Payload, args: RenderArgs, out: Output) -> Result<(), error::Error> {
+    let RenderArgs {
+        clipboard,
+        terminal,
+        preview_terminal,
+        force_kitty,
+        cells,
+        dry_run,
+        json,
+        preview,
+        ..
+    } = args;
+    let options = build_options(payload, args)?;
+    let to_stdout = options
+        .output_path
+        .as_deref()
+        .is_some_and(image_ops::is_stdout);
+
+    let json = json || out.is_silent();
+    if json && to_stdout {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "--json cannot be combined with --output -, as both write to stdout"
+        )));
+    }
+    if preview_terminal.is_some() && (json || to_stdout) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "--preview-terminal writes to stdout, so it can't be combined with --json, \
+             --silent-on-success or --output -"
+        )));
+    }
+
+    let metadata = generate_or_display_qr(
+        &options,
+        terminal,
+        preview_terminal,
+        force_kitty,
+        cells,
+        clipboard,
+    )?;
+
+    if json {
+        let json = serde_json::to_string(&metadata).map_err(|e| error::Error::Anyhow(e.into()))?;
+        println!("{json}");
+    } else if dry_run {
+        for file in &metadata.files {
+            println!(
+                "Would write \"{}\" ({} bytes)",
+                file.path.display(),
+                file.bytes
+            );
+        }
+    } else if clipboard {
+        out.status("QR code successfully generated and copied to the clipboard");
+    } else if to_stdout {
+        out.status("QR code successfully generated and written to stdout");
+    } else if !metadata.files.is_empty() {
+        let paths: Vec<String> = metadata
+            .files
+            .iter()
+            .map(|file| format!("\"{}\"", file.path.display()))
+            .collect();
+        out.status(&format!(
+            "QR code successfully generated and saved to {}",
+            paths.join(", ")
+        ));
+    }
+
+    if preview {
+        match metadata.files.first() {
+            Some(_) if to_stdout => warn!("--preview has no file to open when writing to stdout"),
+            Some(file) => preview::open_in_viewer(&file.path),
+            None => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Render `payload` in memory and check that it decodes back, for the `verify` subcommand.
+fn verify_qr_code(payload: Payload, args: RenderArgs, out: Output) -> Result<(), error::Error> {
+    if args.output.is_some() || args.clipboard || args.data_uri {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "verify doesn't write the QR code anywhere; drop --output, --clipboard and --data-uri"
+        )));
+    }
+    if args.symbology != Symbology::Qr {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "verify can only decode QR codes"
+        )));
+    }
+    let json = args.json || out.is_silent();
+    let options = build_options(payload, args)?;
+    let metadata = qr_generator::verify_qr_code(&options)?;
+
+    if json {
+        let json = serde_json::to_string(&metadata).map_err(|e| error::Error::Anyhow(e.into()))?;
+        println!("{json}");
     } else {
-        rpassword::read_password().context("Could not read password from stdin.")
+        out.status("QR code decodes back to its payload");
     }
+    Ok(())
 }
 
-fn main() -> Result<(), error::Error> {
-    let args = CliArgs::parse();
+/// Validate the rendering options and turn them into the [`QrCodeOptions`] for `payload`.
+fn build_options(payload: Payload, args: RenderArgs) -> Result<QrCodeOptions, error::Error> {
+    let RenderArgs {
+        output,
+        also_output,
+        clipboard,
+        data_uri,
+        terminal: _,
+        preview_terminal: _,
+        force_kitty: _,
+        cells: _,
+        size,
+        scale,
+        snap,
+        dpi,
+        physical_size,
+        print_width_mm,
+        label,
+        label_size,
+        font,
+        frame,
+        frame_width,
+        frame_color,
+        module_shape,
+        module_radius,
+        eye_color,
+        eye_ball_color,
+        eye_style,
+        gradient,
+        gradient_angle,
+        gradient_eyes,
+        background_image,
+        background_opacity,
+        tile,
+        tile_gap,
+        cut_lines,
+        animate,
+        animate_colors,
+        frame_delay,
+        symbology,
+        micro,
+        qr_version,
+        ec_level,
+        format,
+        foreground,
+        background,
+        invert,
+        overwrite,
+        no_prompt,
+        prompt_confirm: _,
+        no_mkdir,
+        no_extension,
+        dry_run,
+        json: _,
+        preview: _,
+    } = args;
 
-    if args.verbose {
-        simple_logger::init().unwrap();
-        info!("Verbose logging enabled.");
+    // A data URI is written to stdout, like --output -.
+    let output = output.or_else(|| data_uri.then(|| PathBuf::from("-")));
+
+    let gradient = match <[String; 2]>::try_from(gradient) {
+        Ok([from, to]) => Some((from, to)),
+        Err(colors) if colors.is_empty() => None,
+        Err(colors) => {
+            return Err(error::Error::Anyhow(anyhow::anyhow!(
+                "--gradient takes exactly two colors, got {}",
+                colors.len()
+            )));
+        }
+    };
+    if !(0.0..=0.5).contains(&module_radius) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "--module-radius must be between 0 and 0.5, got {module_radius}"
+        )));
     }
-    info!("Parsed arguments: {args:#?}");
+    if !(0.0..=1.0).contains(&background_opacity) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "--background-opacity must be between 0 and 1, got {background_opacity}"
+        )));
+    }
+    let label = match label.as_deref() {
+        Some("") => {
+            let caption = payload.caption();
+            if caption.is_none() {
+                warn!("--label needs text for this kind of QR code; no label is drawn");
+            }
+            caption
+        }
+        _ => label,
+    };
+    let label_font = font.as_deref().map(image_ops::load_font_file).transpose()?;
+    let frame_width = frame_width
+        .or(frame.then_some(DEFAULT_FRAME_WIDTH))
+        .filter(|&width| width > 0);
 
-    match args.command {
-        Some(Commands::Generate {
-            ssid,
-            encryption,
-            output,
-            password_file,
-            size,
-            format,
-            foreground,
-            background,
+    let formats = match format.as_slice() {
+        [] if clipboard || data_uri => vec!["png".to_string()],
+        [] if animate
+            && !output
+                .as_deref()
+                .is_some_and(image_ops::has_format_extension) =>
+        {
+            vec!["gif".to_string()]
+        }
+        [] => vec![image_ops::resolve_format(None, output.as_deref())],
+        [format] => vec![image_ops::resolve_format(Some(format), output.as_deref())],
+        formats => formats.iter().map(|format| format.to_lowercase()).collect(),
+    };
+    let to_stdout = output.as_deref().is_some_and(image_ops::is_stdout);
+    if formats.len() > 1 && (to_stdout || clipboard) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "Several formats can only be written to files, not to stdout or the clipboard"
+        )));
+    }
+    if formats.len() > 1 && no_extension {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "--no-extension would write every format to the same file; give a single format"
+        )));
+    }
+    let extra_outputs = also_output
+        .into_iter()
+        .map(|path| match image_ops::format_from_extension(&path) {
+            Some(format) => Ok((path, format)),
+            None => Err(error::Error::Anyhow(anyhow::anyhow!(
+                "--also-output {} needs an extension naming a supported format",
+                path.display()
+            ))),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let all_formats: Vec<&String> = formats
+        .iter()
+        .chain(extra_outputs.iter().map(|(_, format)| format))
+        .collect();
+    let raster_only = match (&background_image, tile) {
+        (Some(_), _) => Some("--background-image"),
+        (None, Some(_)) => Some("--tile"),
+        (None, None) => None,
+    };
+    if let Some((option, format)) = raster_only.zip(
+        all_formats
+            .iter()
+            .find(|format| image_ops::raster_format(format).is_none()),
+    ) {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "{option} needs raster output formats, but {format} was requested"
+        )));
+    }
+    let animate_colors = match animate_colors {
+        colors if colors.is_empty() && animate => {
+            DEFAULT_ANIMATION_COLORS.map(String::from).to_vec()
+        }
+        colors => colors,
+    };
+    if let Some(format) = all_formats
+        .iter()
+        .find(|format| **format != "gif")
+        .filter(|_| !animate_colors.is_empty())
+    {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "Animation needs GIF output, but {format} was requested"
+        )));
+    }
+
+    if symbology == Symbology::Aztec {
+        let qr_only = [
+            (micro, "--micro"),
+            (qr_version.is_some(), "--version"),
+            (module_shape != ModuleShape::Square, "--module-shape"),
+            (eye_style != EyeStyle::Square, "--eye-style"),
+            (eye_color.is_some(), "--eye-color"),
+            (eye_ball_color.is_some(), "--eye-ball-color"),
+            (gradient.is_some(), "--gradient"),
+            (background_image.is_some(), "--background-image"),
+            (tile.is_some(), "--tile"),
+            (!animate_colors.is_empty(), "--animate"),
+            (clipboard, "--clipboard"),
+        ];
+        if let Some((_, option)) = qr_only.iter().find(|(given, _)| *given) {
+            return Err(error::Error::Anyhow(anyhow::anyhow!(
+                "{option} only applies to QR codes, not --symbology aztec"
+            )));
+        }
+        if output.is_none() {
+            return Err(error::Error::Anyhow(anyhow::anyhow!(
+                "Aztec codes can't be shown in the terminal; give --output or --data-uri"
+            )));
+        }
+        if all_formats.iter().any(|format| *format == "eps") {
+            return Err(error::Error::Anyhow(anyhow::anyhow!(
+                "Aztec codes can't be written as EPS"
+            )));
+        }
+    }
+
+    let size = match (physical_size, dpi) {
+        (Some(inches), Some(dpi)) => physical_to_pixels(inches, dpi)?,
+        _ => size,
+    };
+
+    let mut options = QrCodeOptions {
+        payload,
+        output_path: output.clone(),
+        dark_color: foreground,
+        light_color: background,
+        size,
+        scale,
+        snap,
+        physical_size,
+        print_width_mm,
+        label,
+        label_size,
+        label_font,
+        frame_width,
+        frame_color,
+        module_shape,
+        module_radius,
+        eye_color,
+        eye_ball_color,
+        eye_style,
+        gradient,
+        gradient_angle,
+        gradient_eyes,
+        background_image,
+        background_opacity,
+        tile: tile.map(|grid| Tiling {
+            grid,
+            gap: tile_gap,
+            cut_lines,
+        }),
+        animate_colors,
+        frame_delay_ms: frame_delay,
+        symbology,
+        micro,
+        version: qr_version,
+        ec_level,
+        formats,
+        extra_outputs,
+        write: WriteOptions {
             overwrite,
-        }) => {
-            let password = get_password(password_file)
-                .map_err(error::Error::Anyhow)?
-                .trim_end()
-                .to_string();
+            prompt_confirm: !no_prompt,
+            create_dirs: !no_mkdir,
+            exact_path: no_extension,
+            dry_run,
+            data_uri,
+        },
+    };
 
-            validate_hex_color(&foreground).map_err(error::Error::InvalidColor)?;
-            validate_hex_color(&background).map_err(error::Error::InvalidColor)?;
+    if invert {
+        options.invert_colors();
+    }
 
-            let options = QrCodeOptions {
-                ssid,
-                encryption: encryption.to_string(),
-                password,
-                output_path: output.clone(),
-                dark_color: foreground,
-                light_color: background,
-                size,
-                format,
-                overwrite,
+    Ok(options)
+}
+
+/// Generate a QR code for every job read from stdin, using `defaults` for whatever a job leaves
+/// out. Failed jobs are logged and skipped; the run fails at the end if any did.
+fn run_stdin_jobs(defaults: RenderArgs, out: Output) -> Result<(), error::Error> {
+    let mut failed = 0;
+    let mut total = 0;
+    for (index, line) in io::stdin().lock().lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        total += 1;
+
+        let result = Job::parse(&line).and_then(|job| {
+            let payload = job.payload.into_payload()?;
+            let args = RenderArgs {
+                output: Some(job.output),
+                size: job.size.unwrap_or(defaults.size),
+                // A size from the job replaces any other way of sizing the code.
+                scale: defaults.scale.filter(|_| job.size.is_none()),
+                physical_size: defaults.physical_size.filter(|_| job.size.is_none()),
+                format: job
+                    .format
+                    .map_or_else(|| defaults.format.clone(), |f| vec![f]),
+                ..defaults.clone()
             };
+            render_qr_code(payload, args, out)
+        });
+        if let Err(e) = result {
+            failed += 1;
+            error!("Job on line {} failed: {e}", index + 1);
+        }
+    }
 
-            generate_or_display_qr(&options)?;
+    if failed > 0 {
+        return Err(error::Error::Anyhow(anyhow::anyhow!(
+            "{failed} of {total} jobs failed"
+        )));
+    }
+    Ok(())
+}
+
+/// Exit code for failures reported by `--silent-on-success`.
+const EXIT_FAILURE: i32 = 1;
+
+/// Exit code for invalid arguments, matching clap's.
+const EXIT_USAGE: i32 = 2;
+
+/// Print `kind` and `message` as the single JSON error object of `--silent-on-success`.
+fn print_json_error(kind: &str, message: &str) {
+    let error = serde_json::json!({ "error": { "kind": kind, "message": message } });
+    eprintln!("{error}");
+}
 
-            if let Some(path) = options.output_path {
-                println!(
-                    "QR code successfully generated and saved to \"{}\"",
-                    path.display()
-                );
+/// Whether `--silent-on-success` was given, as far as clap can tell from arguments that failed
+/// to parse.
+fn silent_despite_errors() -> bool {
+    CliArgs::command()
+        .ignore_errors(true)
+        .try_get_matches()
+        .is_ok_and(|matches| {
+            matches
+                .try_get_one::<bool>("silent_on_success")
+                .is_ok_and(|silent| silent == Some(&true))
+        })
+}
+
+fn main() -> Result<(), error::Error> {
+    let args = match CliArgs::try_parse() {
+        Ok(args) => args,
+        Err(e) if e.use_stderr() && silent_despite_errors() => {
+            let message = e.to_string();
+            let first_line = message.lines().next().unwrap_or_default();
+            print_json_error("usage", first_line.trim_start_matches("error: "));
+            std::process::exit(EXIT_USAGE);
+        }
+        Err(e) => e.exit(),
+    };
+
+    let silent = args.silent_on_success;
+    match run(args) {
+        Err(e) if silent => {
+            print_json_error(e.kind(), &e.to_string());
+            std::process::exit(EXIT_FAILURE);
+        }
+        result => result,
+    }
+}
+
+fn run(args: CliArgs) -> Result<(), error::Error> {
+    let out = Output::new(
+        args.verbose,
+        args.quiet,
+        args.silent_on_success,
+        args.log_format,
+    );
+    out.init_logging()?;
+    info!("Parsed arguments: {args:#?}");
+
+    match args.command {
+        Some(Commands::Generate { wifi, render }) => {
+            render_qr_code(wifi.into_payload()?, render, out)?
+        }
+        Some(Commands::Verify { wifi, render }) => {
+            verify_qr_code(wifi.into_payload()?, render, out)?
+        }
+        Some(Commands::Text {
+            data,
+            file,
+            binary,
+            render,
+        }) => {
+            let payload = match (data, file) {
+                (Some(data), _) => Payload::Text { data },
+                (None, Some(path)) if binary => Payload::Binary {
+                    bytes: std::fs::read(&path).map_err(|e| {
+                        error::Error::Anyhow(anyhow::anyhow!(
+                            "Could not read file: {}: {e}",
+                            path.display()
+                        ))
+                    })?,
+                },
+                (None, Some(path)) => Payload::Text {
+                    data: read_input(InputSource::File(path)).map_err(error::Error::Anyhow)?,
+                },
+                (None, None) => unreachable!("clap requires --data or --file"),
+            };
+            render_qr_code(payload, render, out)?;
+        }
+        Some(Commands::Tel { number, render }) => {
+            let number = payload::normalize_phone_number(&number)
+                .map_err(error::Error::InvalidPhoneNumber)?;
+            render_qr_code(Payload::Tel { number }, render, out)?;
+        }
+        Some(Commands::Crypto {
+            coin,
+            address,
+            amount,
+            render,
+        }) => {
+            payload::validate_crypto_address(coin, &address)
+                .map_err(error::Error::InvalidAddress)?;
+            if let Some(amount) = &amount {
+                payload::validate_amount(amount)
+                    .map_err(|e| error::Error::Anyhow(anyhow::anyhow!(e)))?;
+            }
+            let payload = Payload::Crypto {
+                coin,
+                address,
+                amount,
+            };
+            render_qr_code(payload, render, out)?;
+        }
+        Some(Commands::Whatsapp {
+            number,
+            message,
+            render,
+        }) => {
+            let number = payload::normalize_phone_number(&number)
+                .map_err(error::Error::InvalidPhoneNumber)?;
+            let payload = Payload::WhatsApp {
+                number: number.trim_start_matches('+').to_string(),
+                message,
+            };
+            render_qr_code(payload, render, out)?;
+        }
+        Some(Commands::Telegram { username, render }) => {
+            let username = payload::normalize_telegram_username(&username)
+                .map_err(error::Error::InvalidUsername)?;
+            render_qr_code(Payload::Telegram { username }, render, out)?;
+        }
+        Some(Commands::Encode { kind, wifi, render }) => {
+            let payload = read_stdin_payload(kind, wifi)?;
+            render_qr_code(payload, render, out)?;
+        }
+        Some(Commands::Completions { shell }) => {
+            let mut command = CliArgs::command();
+            let name = command.get_name().to_string();
+            clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+        }
+        Some(Commands::Man { output }) => {
+            let man = clap_mangen::Man::new(CliArgs::command());
+            match output {
+                Some(path) => {
+                    let mut file = BufWriter::new(File::create(&path)?);
+                    man.render(&mut file)?;
+                    file.flush()?;
+                    out.status(&format!("Man page written to \"{}\"", path.display()));
+                }
+                None => man.render(&mut io::stdout())?,
             }
         }
+        Some(Commands::StdinJobs { render }) => run_stdin_jobs(render, out)?,
         None => {}
     }
 
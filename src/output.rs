@@ -0,0 +1,183 @@
+use crate::error::Error;
+use clap::ValueEnum;
+use log::{LevelFilter, Log, Metadata, Record, info};
+use time::{OffsetDateTime, format_description::well_known::Rfc3339};
+
+/// How much the CLI reports besides the generated image itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Verbosity {
+    /// Only errors.
+    Quiet,
+    /// Warnings and status messages.
+    Normal,
+    /// Informational logs as well, unless `RUST_LOG` asks for another level.
+    Verbose,
+    /// Nothing but the JSON result on stdout, or a JSON error on stderr.
+    Silent,
+}
+
+/// How log records are written to stderr.
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum LogFormat {
+    /// Human-readable lines.
+    #[default]
+    Text,
+    /// One JSON object per line, with `timestamp`, `level`, `target` and `message` fields.
+    Json,
+}
+
+/// Routes status messages and log output according to the selected [`Verbosity`].
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Output {
+    verbosity: Verbosity,
+    log_format: LogFormat,
+}
+
+impl Output {
+    pub(crate) fn new(verbose: bool, quiet: bool, silent: bool, log_format: LogFormat) -> Self {
+        let verbosity = match (verbose, quiet, silent) {
+            (_, _, true) => Verbosity::Silent,
+            (true, _, _) => Verbosity::Verbose,
+            (_, true, _) => Verbosity::Quiet,
+            _ => Verbosity::Normal,
+        };
+        Self {
+            verbosity,
+            log_format,
+        }
+    }
+
+    /// Install the logger. All log output goes to stderr, so piping an image from `--output -`
+    /// stays clean.
+    ///
+    /// A level in `RUST_LOG` (e.g. `debug`) overrides the default set by `--verbose`, but not
+    /// `--quiet`. Other crates, such as usvg complaining about odd SVG attributes, only log errors
+    /// unless one of those asks for more.
+    pub(crate) fn init_logging(&self) -> Result<(), Error> {
+        let level = self.log_level();
+        let dependency_level = self.dependency_log_level();
+        let installed = match self.log_format {
+            LogFormat::Text => simple_logger::SimpleLogger::new()
+                .with_level(dependency_level)
+                .with_module_level(CRATE_TARGET, level)
+                .init(),
+            LogFormat::Json => log::set_boxed_logger(Box::new(JsonLogger {
+                level,
+                dependency_level,
+            }))
+            .map(|()| log::set_max_level(level)),
+        };
+        installed
+            .map_err(|e| Error::Anyhow(anyhow::anyhow!("Could not install the logger: {e}")))?;
+        info!("Verbose logging enabled.");
+        Ok(())
+    }
+
+    fn log_level(&self) -> LevelFilter {
+        let from_env = || {
+            std::env::var("RUST_LOG")
+                .ok()
+                .and_then(|level| level.parse().ok())
+        };
+        match self.verbosity {
+            Verbosity::Silent => LevelFilter::Off,
+            Verbosity::Quiet => LevelFilter::Error,
+            Verbosity::Normal => from_env().unwrap_or(LevelFilter::Warn),
+            Verbosity::Verbose => from_env().unwrap_or(LevelFilter::Info),
+        }
+    }
+
+    /// Level for records from other crates: at most errors on a default run, as their warnings
+    /// are about internals the user can't act on.
+    fn dependency_log_level(&self) -> LevelFilter {
+        let level = self.log_level();
+        match self.verbosity {
+            Verbosity::Normal if std::env::var_os("RUST_LOG").is_none() => {
+                level.min(LevelFilter::Error)
+            }
+            _ => level,
+        }
+    }
+
+    /// Whether only machine-readable JSON should be printed.
+    pub(crate) fn is_silent(&self) -> bool {
+        self.verbosity == Verbosity::Silent
+    }
+
+    /// Print a human-readable status message to stderr, unless running quietly.
+    pub(crate) fn status(&self, message: &str) {
+        if !matches!(self.verbosity, Verbosity::Quiet | Verbosity::Silent) {
+            eprintln!("{message}");
+        }
+    }
+}
+
+/// Log target prefix of this crate's own records.
+const CRATE_TARGET: &str = env!("CARGO_CRATE_NAME");
+
+/// Whether `target` belongs to another crate, which [`Output::dependency_log_level`] applies to.
+fn is_dependency(target: &str) -> bool {
+    target
+        .strip_prefix(CRATE_TARGET)
+        .is_none_or(|rest| !(rest.is_empty() || rest.starts_with("::")))
+}
+
+/// Writes each log record to stderr as a single line of JSON, for log aggregators.
+struct JsonLogger {
+    level: LevelFilter,
+    dependency_level: LevelFilter,
+}
+
+impl Log for JsonLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        let level = if is_dependency(metadata.target()) {
+            self.dependency_level
+        } else {
+            self.level
+        };
+        metadata.level() <= level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let timestamp = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .unwrap_or_default();
+        let line = serde_json::json!({
+            "timestamp": timestamp,
+            "level": record.level().as_str(),
+            "target": record.target(),
+            "message": record.args().to_string(),
+        });
+        eprintln!("{line}");
+    }
+
+    fn flush(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn other_crates_only_log_errors_by_default() {
+        assert!(is_dependency("usvg::parser"));
+        assert!(is_dependency("ciphercanvas_extra"));
+        assert!(!is_dependency("ciphercanvas::qr_generator"));
+
+        let logger = JsonLogger {
+            level: LevelFilter::Warn,
+            dependency_level: LevelFilter::Error,
+        };
+        let warning = |target| {
+            Metadata::builder()
+                .level(log::Level::Warn)
+                .target(target)
+                .build()
+        };
+        assert!(logger.enabled(&warning("ciphercanvas::qr_generator")));
+        assert!(!logger.enabled(&warning("usvg::parser")));
+    }
+}
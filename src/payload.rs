@@ -0,0 +1,479 @@
+use clap::ValueEnum;
+
+/// The data encoded in a QR code.
+#[derive(Debug, Clone)]
+pub enum Payload {
+    /// Arbitrary text, encoded as given.
+    Text { data: String },
+    /// Raw bytes, e.g. read from a file with `text --binary`. They needn't be valid UTF-8.
+    Binary { bytes: Vec<u8> },
+    /// Wi-Fi network credentials.
+    Wifi {
+        ssid: String,
+        encryption: String,
+        password: String,
+    },
+    /// A phone number to call, already normalized by [`normalize_phone_number`].
+    Tel { number: String },
+    /// A cryptocurrency payment request, checked by [`validate_crypto_address`].
+    Crypto {
+        coin: Coin,
+        address: String,
+        amount: Option<String>,
+    },
+    /// A WhatsApp chat link. The number is normalized and has no leading `+`.
+    WhatsApp {
+        number: String,
+        message: Option<String>,
+    },
+    /// A Telegram profile link, checked by [`normalize_telegram_username`].
+    Telegram { username: String },
+}
+
+/// Cryptocurrencies the `crypto` subcommand can request payment in.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Coin {
+    /// Bitcoin.
+    Btc,
+    /// Ethereum.
+    Eth,
+    /// Litecoin.
+    Ltc,
+}
+
+impl Coin {
+    /// The URI scheme wallets register for this coin.
+    fn scheme(self) -> &'static str {
+        match self {
+            Coin::Btc => "bitcoin",
+            Coin::Eth => "ethereum",
+            Coin::Ltc => "litecoin",
+        }
+    }
+}
+
+impl Payload {
+    /// The text stored in the QR code. Binary payloads that aren't valid UTF-8 are shown with
+    /// replacement characters; use [`Payload::bytes`] for what is actually encoded.
+    pub fn contents(&self) -> String {
+        match self {
+            Payload::Text { data } => data.clone(),
+            Payload::Binary { bytes } => String::from_utf8_lossy(bytes).into_owned(),
+            Payload::Wifi {
+                ssid,
+                encryption,
+                password,
+            } => build_wifi_qr_payload(ssid, encryption, password),
+            Payload::Tel { number } => format!("tel:{number}"),
+            Payload::Crypto {
+                coin,
+                address,
+                amount,
+            } => match amount {
+                Some(amount) => format!("{}:{address}?amount={amount}", coin.scheme()),
+                None => format!("{}:{address}", coin.scheme()),
+            },
+            Payload::WhatsApp { number, message } => match message {
+                Some(message) => format!("https://wa.me/{number}?text={}", url_encode(message)),
+                None => format!("https://wa.me/{number}"),
+            },
+            Payload::Telegram { username } => format!("https://t.me/{username}"),
+        }
+    }
+
+    /// The bytes stored in the QR code.
+    pub fn bytes(&self) -> Vec<u8> {
+        match self {
+            Payload::Binary { bytes } => bytes.clone(),
+            _ => self.contents().into_bytes(),
+        }
+    }
+
+    /// A short human-readable name for the code, used as its label when none is given.
+    pub fn caption(&self) -> Option<String> {
+        match self {
+            Payload::Wifi { ssid, .. } => Some(ssid.clone()),
+            Payload::Tel { number } => Some(number.clone()),
+            Payload::WhatsApp { number, .. } => Some(format!("+{number}")),
+            Payload::Telegram { username } => Some(format!("@{username}")),
+            Payload::Text { .. } | Payload::Binary { .. } | Payload::Crypto { .. } => None,
+        }
+    }
+}
+
+/// Backslash-escape the characters that are special in a Wi-Fi payload field: `\`, `;`, `,`,
+/// `:` and `"`. Without this a `;` in a password would end the field early.
+fn escape_wifi_value(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for c in input.chars() {
+        if matches!(c, '\\' | ';' | ',' | ':' | '"') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Build the standard Wi-Fi QR code payload string.
+///
+/// Format: `WIFI:S:<ssid>;T:<encryption>;P:<password>;;`
+/// See: <https://github.com/zxing/zxing/wiki/Barcode-Contents#wi-fi-network-config-android-ios-11>
+///
+/// Open networks (`nopass`) leave out the `P:` field, as some scanners expect.
+fn build_wifi_qr_payload(ssid: &str, encryption: &str, password: &str) -> String {
+    let ssid_escaped = escape_wifi_value(ssid);
+    let encryption_escaped = escape_wifi_value(&encryption.to_uppercase());
+    if encryption.eq_ignore_ascii_case("nopass") {
+        return format!("WIFI:S:{ssid_escaped};T:{encryption_escaped};;");
+    }
+    let password_escaped = escape_wifi_value(password);
+    format!(
+        "WIFI:S:{};T:{};P:{};;",
+        ssid_escaped, encryption_escaped, password_escaped
+    )
+}
+
+/// Check that `password` has a length the network's encryption (a Wi-Fi `T:` value) accepts,
+/// since a code with an out-of-range password never connects.
+///
+/// WPA and WPA2 passphrases are 8 to 63 characters, or a 64-digit hex key. WEP keys are 5 or 13
+/// characters, or 10 or 26 hex digits. WPA3 (`SAE`) only needs a password, and open networks
+/// (`nopass`) must not have one.
+pub fn check_wifi_password(encryption: &str, password: &str) -> Result<(), String> {
+    let length = password.chars().count();
+    let is_hex = password.chars().all(|c| c.is_ascii_hexdigit());
+    let problem = match encryption.to_uppercase().as_str() {
+        "WPA" | "WPA2" if !((8..=63).contains(&length) || length == 64 && is_hex) => {
+            format!("WPA passphrases are 8 to 63 characters, but this one has {length}")
+        }
+        "WEP" if !(matches!(length, 5 | 13) || matches!(length, 10 | 26) && is_hex) => {
+            format!(
+                "WEP keys are 5 or 13 characters, or 10 or 26 hex digits, but this one has \
+                 {length} characters"
+            )
+        }
+        "SAE" if password.is_empty() => "WPA3 networks need a password".to_string(),
+        "NOPASS" if !password.is_empty() => {
+            "open networks have no password, so it is left out of the code".to_string()
+        }
+        _ => return Ok(()),
+    };
+    Err(problem)
+}
+
+/// Longest phone number E.164 allows, not counting the leading `+`.
+const MAX_PHONE_DIGITS: usize = 15;
+
+/// Normalize a phone number by dropping the spaces, dashes, dots and parentheses people use to
+/// group digits, keeping a leading `+`.
+///
+/// Anything other than digits is rejected, as is a number longer than E.164 allows.
+pub fn normalize_phone_number(number: &str) -> Result<String, String> {
+    let trimmed = number.trim();
+    let (plus, rest) = match trimmed.strip_prefix('+') {
+        Some(rest) => ("+", rest),
+        None => ("", trimmed),
+    };
+    let digits: String = rest
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '-' | '.' | '(' | ')'))
+        .collect();
+
+    if digits.is_empty() {
+        return Err(format!("Phone number has no digits: {number:?}"));
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(format!(
+            "Phone number may only contain digits, spaces and dashes: {number:?} (found {c:?})"
+        ));
+    }
+    if digits.len() > MAX_PHONE_DIGITS {
+        return Err(format!(
+            "Phone number is longer than {MAX_PHONE_DIGITS} digits: {number:?}"
+        ));
+    }
+    Ok(format!("{plus}{digits}"))
+}
+
+/// Percent-encode `input` for use in a URL query value. Only unreserved characters are kept as
+/// they are; everything else, including spaces, is encoded byte by byte as UTF-8.
+fn url_encode(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for byte in input.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => out.push_str(&format!("%{byte:02X}")),
+        }
+    }
+    out
+}
+
+/// Strip a leading `@` from a Telegram username and check it has 5 to 32 letters, digits or
+/// underscores, starting with a letter.
+pub fn normalize_telegram_username(username: &str) -> Result<String, String> {
+    let name = username.trim().trim_start_matches('@');
+    let valid = (5..=32).contains(&name.len())
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(format!(
+            "Telegram usernames have 5 to 32 letters, digits or underscores and start with a \
+             letter: {username:?}"
+        ))
+    }
+}
+
+/// Characters of the Base58 alphabet used by legacy Bitcoin and Litecoin addresses.
+const BASE58_ALPHABET: &str = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// Characters of the Bech32 alphabet used by SegWit addresses, after the `1` separator.
+const BECH32_ALPHABET: &str = "qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Check that `address` looks like an address for `coin`: the right prefix, length and
+/// character set. Checksums aren't verified, so this only catches obvious mistakes such as an
+/// Ethereum address given for Bitcoin.
+pub fn validate_crypto_address(coin: Coin, address: &str) -> Result<(), String> {
+    let valid = match coin {
+        Coin::Btc => is_base58_address(address, &['1', '3']) || is_bech32_address(address, "bc1"),
+        Coin::Eth => {
+            address.len() == 42
+                && address.starts_with("0x")
+                && address[2..].chars().all(|c| c.is_ascii_hexdigit())
+        }
+        Coin::Ltc => {
+            is_base58_address(address, &['L', 'M', '3']) || is_bech32_address(address, "ltc1")
+        }
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "{address:?} is not a valid {} address",
+            coin.scheme()
+        ))
+    }
+}
+
+fn is_base58_address(address: &str, prefixes: &[char]) -> bool {
+    (26..=35).contains(&address.len())
+        && address.starts_with(prefixes)
+        && address.chars().all(|c| BASE58_ALPHABET.contains(c))
+}
+
+fn is_bech32_address(address: &str, prefix: &str) -> bool {
+    // Bech32 is case-insensitive, but mixing cases is invalid.
+    let lower = address.to_lowercase();
+    if address != lower && address != address.to_uppercase() {
+        return false;
+    }
+    lower.len() <= 90
+        && lower.strip_prefix(prefix).is_some_and(|data| {
+            data.len() >= 6 && data.chars().all(|c| BECH32_ALPHABET.contains(c))
+        })
+}
+
+/// Check that `amount` is a positive decimal number such as `0.005`, as BIP-21 expects.
+pub fn validate_amount(amount: &str) -> Result<(), String> {
+    let (whole, fraction) = amount.split_once('.').unwrap_or((amount, ""));
+    let digits_only = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let positive = amount.chars().any(|c| matches!(c, '1'..='9'));
+    if whole.is_empty() || !digits_only(whole) || !digits_only(fraction) || !positive {
+        return Err(format!(
+            "Amount must be a positive decimal number like 0.005: {amount:?}"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn binary_payloads_keep_their_bytes() {
+        let bytes = vec![0x00, 0xff, 0xfe, b'\n'];
+        let payload = Payload::Binary {
+            bytes: bytes.clone(),
+        };
+        assert_eq!(payload.bytes(), bytes);
+        assert_eq!(payload.contents(), "\0\u{fffd}\u{fffd}\n");
+    }
+
+    #[test]
+    fn wifi_qr_format_basic() {
+        let payload = build_wifi_qr_payload("MyNetwork", "WPA", "secret123");
+        assert_eq!(payload, "WIFI:S:MyNetwork;T:WPA;P:secret123;;");
+    }
+
+    #[test]
+    fn wifi_qr_format_none_encryption() {
+        let payload = build_wifi_qr_payload("GuestWifi", "None", "nopass");
+        assert_eq!(payload, "WIFI:S:GuestWifi;T:NONE;P:nopass;;");
+    }
+
+    #[test]
+    fn wifi_qr_nopass_omits_password() {
+        let payload = build_wifi_qr_payload("GuestWifi", "nopass", "");
+        assert_eq!(payload, "WIFI:S:GuestWifi;T:NOPASS;;");
+    }
+
+    #[test]
+    fn wifi_qr_format_lowercase_encryption_uppercased() {
+        let payload = build_wifi_qr_payload("Home", "wpa", "password");
+        assert_eq!(payload, "WIFI:S:Home;T:WPA;P:password;;");
+    }
+
+    #[test]
+    fn wifi_qr_format_wep() {
+        let payload = build_wifi_qr_payload("OldNetwork", "WEP", "wepkey");
+        assert_eq!(payload, "WIFI:S:OldNetwork;T:WEP;P:wepkey;;");
+    }
+
+    #[test]
+    fn wifi_qr_empty_ssid() {
+        let payload = build_wifi_qr_payload("", "WPA", "password");
+        assert_eq!(payload, "WIFI:S:;T:WPA;P:password;;");
+    }
+
+    #[test]
+    fn wifi_qr_empty_password() {
+        let payload = build_wifi_qr_payload("MyNetwork", "None", "");
+        assert_eq!(payload, "WIFI:S:MyNetwork;T:NONE;P:;;");
+    }
+
+    #[test]
+    fn wifi_qr_empty_wpa_password() {
+        let payload = build_wifi_qr_payload("MyNetwork", "WPA", "");
+        assert_eq!(payload, "WIFI:S:MyNetwork;T:WPA;P:;;");
+    }
+
+    #[test]
+    fn wifi_password_lengths_are_checked() {
+        assert!(check_wifi_password("WPA", "secret123").is_ok());
+        assert!(check_wifi_password("WPA2", &"a".repeat(63)).is_ok());
+        assert!(check_wifi_password("WPA", &"f".repeat(64)).is_ok());
+        assert!(check_wifi_password("WPA", "short").is_err());
+        assert!(check_wifi_password("WPA", &"g".repeat(64)).is_err());
+        assert!(check_wifi_password("WEP", "abcde").is_ok());
+        assert!(check_wifi_password("WEP", "0123456789").is_ok());
+        assert!(check_wifi_password("WEP", "abcdef").is_err());
+        assert!(check_wifi_password("WEP", "ghijklmnop").is_err());
+        assert!(check_wifi_password("SAE", "").is_err());
+        assert!(check_wifi_password("nopass", "").is_ok());
+        assert!(check_wifi_password("nopass", "secret123").is_err());
+    }
+
+    #[test]
+    fn wifi_qr_special_chars_in_ssid() {
+        let payload = build_wifi_qr_payload("My\\Network", "WPA", "pass\\word");
+        assert_eq!(payload, "WIFI:S:My\\\\Network;T:WPA;P:pass\\\\word;;");
+    }
+
+    #[test]
+    fn wifi_qr_escapes_field_separators() {
+        let payload = build_wifi_qr_payload("Caf\u{e9} \"Guest\"", "WPA", "a;b,c:d\\e");
+        assert_eq!(
+            payload,
+            "WIFI:S:Caf\u{e9} \\\"Guest\\\";T:WPA;P:a\\;b\\,c\\:d\\\\e;;"
+        );
+    }
+
+    #[test]
+    fn phone_number_separators_are_stripped() {
+        assert_eq!(
+            normalize_phone_number("+1 555-123 4567").unwrap(),
+            "+15551234567"
+        );
+        assert_eq!(
+            normalize_phone_number("(030) 1234.56").unwrap(),
+            "030123456"
+        );
+        assert_eq!(
+            Payload::Tel {
+                number: "+15551234567".to_string()
+            }
+            .contents(),
+            "tel:+15551234567"
+        );
+    }
+
+    #[test]
+    fn invalid_phone_numbers_are_rejected() {
+        assert!(normalize_phone_number("").is_err());
+        assert!(normalize_phone_number("+").is_err());
+        assert!(normalize_phone_number("555-CALL-NOW").is_err());
+        assert!(normalize_phone_number("1+555").is_err());
+        assert!(normalize_phone_number("1234567890123456").is_err());
+    }
+
+    #[test]
+    fn crypto_payment_uri() {
+        let payload = Payload::Crypto {
+            coin: Coin::Btc,
+            address: "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq".to_string(),
+            amount: Some("0.005".to_string()),
+        };
+        assert_eq!(
+            payload.contents(),
+            "bitcoin:bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq?amount=0.005"
+        );
+    }
+
+    #[test]
+    fn crypto_addresses_are_checked_per_coin() {
+        let eth = "0x52908400098527886E0F7030069857D2E4169EE7";
+        assert!(validate_crypto_address(Coin::Btc, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").is_ok());
+        assert!(
+            validate_crypto_address(Coin::Btc, "bc1qar0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+                .is_ok()
+        );
+        assert!(validate_crypto_address(Coin::Eth, eth).is_ok());
+        assert!(validate_crypto_address(Coin::Ltc, "LQTpS3VaYTjCr4s9Y1t5zbeY26zevf7Fb3").is_ok());
+
+        assert!(validate_crypto_address(Coin::Btc, eth).is_err());
+        assert!(validate_crypto_address(Coin::Eth, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNVN2").is_err());
+        assert!(
+            validate_crypto_address(Coin::Btc, "bc1qAr0srrr7xfkvy5l643lydnw9re59gtzzwf5mdq")
+                .is_err()
+        );
+        assert!(validate_crypto_address(Coin::Btc, "1BvBMSEYstWetqTFn5Au4m4GFg7xJaNV0l").is_err());
+    }
+
+    #[test]
+    fn amounts_must_be_positive_decimals() {
+        assert!(validate_amount("0.005").is_ok());
+        assert!(validate_amount("12").is_ok());
+        assert!(validate_amount("0").is_err());
+        assert!(validate_amount("-1").is_err());
+        assert!(validate_amount(".5").is_err());
+        assert!(validate_amount("1e3").is_err());
+        assert!(validate_amount("1.2.3").is_err());
+    }
+
+    #[test]
+    fn whatsapp_message_is_url_encoded() {
+        let payload = Payload::WhatsApp {
+            number: "15551234567".to_string(),
+            message: Some("Table 4, café & bar?".to_string()),
+        };
+        assert_eq!(
+            payload.contents(),
+            "https://wa.me/15551234567?text=Table%204%2C%20caf%C3%A9%20%26%20bar%3F"
+        );
+    }
+
+    #[test]
+    fn telegram_usernames_are_checked() {
+        assert_eq!(
+            normalize_telegram_username("@ciphercanvas").unwrap(),
+            "ciphercanvas"
+        );
+        assert!(normalize_telegram_username("abc").is_err());
+        assert!(normalize_telegram_username("1canvas").is_err());
+        assert!(normalize_telegram_username("cipher-canvas").is_err());
+    }
+}
@@ -0,0 +1,53 @@
+use log::{info, warn};
+use std::path::Path;
+
+/// Open `path` with the system's default application, e.g. an image viewer.
+///
+/// On CI runners and machines without a graphical session there's nothing to show it on, so a
+/// warning is logged instead. Failing to open it is only a warning too, as the file was written.
+pub(crate) fn open_in_viewer(path: &Path) {
+    if let Some(reason) = headless_reason(|name| std::env::var_os(name).is_some()) {
+        warn!("Not opening {} for preview: {reason}", path.display());
+        return;
+    }
+    match open::that_detached(path) {
+        Ok(()) => info!("Opened {} in the default viewer", path.display()),
+        Err(e) => warn!("Could not open {} for preview: {e}", path.display()),
+    }
+}
+
+/// Why no viewer can be shown, judging by which environment variables are set, or `None` if one
+/// probably can.
+///
+/// Only Linux and the BSDs need a display server; macOS and Windows always have a desktop.
+fn headless_reason(is_set: impl Fn(&str) -> bool) -> Option<&'static str> {
+    if is_set("CI") {
+        return Some("running in CI");
+    }
+    if cfg!(all(unix, not(target_os = "macos"))) && !is_set("DISPLAY") && !is_set("WAYLAND_DISPLAY")
+    {
+        return Some("no graphical session ($DISPLAY and $WAYLAND_DISPLAY are unset)");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ci_is_headless() {
+        assert!(headless_reason(|name| matches!(name, "CI" | "DISPLAY")).is_some());
+    }
+
+    #[test]
+    fn desktop_session_can_preview() {
+        assert_eq!(headless_reason(|name| name == "WAYLAND_DISPLAY"), None);
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn missing_display_is_headless() {
+        assert!(headless_reason(|_| false).is_some());
+    }
+}
@@ -0,0 +1,42 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+/// Ask a yes/no question on the terminal, defaulting to "no".
+///
+/// Returns `Ok(None)` when stdin isn't interactive, so callers can fall back to their
+/// non-interactive behavior instead of blocking a script.
+pub(crate) fn confirm(question: &str) -> io::Result<Option<bool>> {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() {
+        return Ok(None);
+    }
+
+    let mut stderr = io::stderr().lock();
+    write!(stderr, "{question} [y/N] ")?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    stdin.lock().read_line(&mut answer)?;
+    Ok(Some(is_yes(&answer)))
+}
+
+fn is_yes(answer: &str) -> bool {
+    matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn yes_answers_are_accepted() {
+        assert!(is_yes("y\n"));
+        assert!(is_yes("YES\r\n"));
+    }
+
+    #[test]
+    fn anything_else_defaults_to_no() {
+        assert!(!is_yes("\n"));
+        assert!(!is_yes("n\n"));
+        assert!(!is_yes("yep\n"));
+    }
+}
@@ -1,142 +1,1567 @@
-use crate::{error::Error, image_ops::save_image};
+use crate::{
+    aztec::{self, AztecCode},
+    error::Error,
+    image_ops::{
+        DEFAULT_FORMAT, RenderedImage, SavedImage, Tiling, WriteOptions, composite_over_background,
+        encode_gif, output_path_for_format, raster_format, save_image, svg_to_rgba_image,
+        tile_image, write_image,
+    },
+    payload::Payload,
+    svg as svg_doc,
+};
+use clap::ValueEnum;
+use image::RgbaImage;
 use log::{info, warn};
 use miette::Result;
-use qrcode::{EcLevel, QrCode, render::svg};
-use std::path::PathBuf;
+use qrcode::{
+    Color, EcLevel, QrCode, Version,
+    render::{svg, unicode::Dense1x2},
+};
+use serde::Serialize;
+use std::{borrow::Cow, fmt::Write as _, path::PathBuf};
+use terminal_size::Width;
 
-#[cfg(feature = "kitty_graphics")]
-use crate::image_ops::load_svg;
-#[cfg(feature = "kitty_graphics")]
-use kitty_image::{Action, ActionPut, ActionTransmission, Command, Format, Medium, WrappedCommand};
-#[cfg(feature = "kitty_graphics")]
+#[cfg(any(feature = "kitty_graphics", feature = "iterm2_graphics"))]
+use crate::image_ops::encode_rgba;
+
+#[cfg(feature = "clipboard")]
+use arboard::Clipboard;
+#[cfg(any(feature = "kitty_graphics", feature = "iterm2_graphics"))]
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+#[cfg(any(feature = "kitty_graphics", feature = "iterm2_graphics"))]
 use std::io::Write;
 
+/// How individual modules are drawn in SVG and raster output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ModuleShape {
+    /// Plain squares, as drawn by the `qrcode` renderer.
+    #[default]
+    Square,
+    /// Circles.
+    Dots,
+    /// Squares with rounded corners.
+    Rounded,
+}
+
+/// How the three finder patterns ("eyes") are drawn in SVG and raster output.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EyeStyle {
+    /// Square rings around square centers, module by module.
+    #[default]
+    Square,
+    /// Rings and centers with rounded corners.
+    Rounded,
+    /// Circular rings around round centers.
+    Circle,
+}
+
+/// The kind of 2D barcode to generate.
+#[derive(ValueEnum, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Symbology {
+    /// A QR code, which every phone camera scans.
+    #[default]
+    Qr,
+    /// An Aztec code, used by some transit and ticketing systems. Only plain square modules.
+    Aztec,
+}
+
+/// Error correction level of regular QR codes, as chosen with `--ec-level`.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorCorrection {
+    /// Recovers about 7% of the code.
+    L,
+    /// Recovers about 15% of the code.
+    M,
+    /// Recovers about 25% of the code.
+    Q,
+    /// Recovers about 30% of the code.
+    #[default]
+    H,
+    /// The smallest code the payload fits in, with the strongest level that keeps it that size.
+    Auto,
+}
+
+impl ErrorCorrection {
+    /// The fixed level, or `None` for [`ErrorCorrection::Auto`].
+    fn level(self) -> Option<EcLevel> {
+        match self {
+            ErrorCorrection::L => Some(EcLevel::L),
+            ErrorCorrection::M => Some(EcLevel::M),
+            ErrorCorrection::Q => Some(EcLevel::Q),
+            ErrorCorrection::H => Some(EcLevel::H),
+            ErrorCorrection::Auto => None,
+        }
+    }
+}
+
+/// Most bytes a version 40 QR code holds in byte mode at `ec_level`. Automatic error correction
+/// can drop to level L, so it has the same limit.
+fn max_payload_bytes(ec_level: ErrorCorrection) -> usize {
+    match ec_level {
+        ErrorCorrection::L | ErrorCorrection::Auto => 2953,
+        ErrorCorrection::M => 2331,
+        ErrorCorrection::Q => 1663,
+        ErrorCorrection::H => 1273,
+    }
+}
+
+#[derive(Clone)]
 pub struct QrCodeOptions {
-    pub ssid: String,
-    pub encryption: String,
-    pub password: String,
+    pub payload: Payload,
     pub output_path: Option<PathBuf>,
     pub dark_color: String,
     pub light_color: String,
     pub size: u32,
+    /// Pixels per module. When set, the image size is derived from the module count instead of
+    /// `size`.
+    pub scale: Option<u32>,
+    /// Round `size` up to a whole number of pixels per module.
+    pub snap: bool,
+    /// Physical edge length in inches, embedded in SVG output so it prints at that size.
+    pub physical_size: Option<f64>,
+    /// Intended printed width in millimeters, used to report the effective print resolution.
+    pub print_width_mm: Option<f64>,
+    /// Text drawn centered below the code.
+    pub label: Option<String>,
+    /// Label font size in pixels; derived from the image size when unset.
+    pub label_size: Option<u32>,
+    /// Font family for the label, loaded with `image_ops::load_font_file`; the bundled font when
+    /// unset.
+    pub label_font: Option<String>,
+    /// Width of a solid border drawn outside the quiet zone, if any.
+    pub frame_width: Option<u32>,
+    /// Border color; defaults to `dark_color`.
+    pub frame_color: Option<String>,
+    /// Shape of the data modules. Finder patterns follow `eye_style` instead.
+    pub module_shape: ModuleShape,
+    /// Corner radius of [`ModuleShape::Rounded`] modules, as a fraction of the module size.
+    pub module_radius: f64,
+    /// Color of the finder patterns' outer ring; defaults to the foreground color.
+    pub eye_color: Option<String>,
+    /// Color of the finder patterns' center; defaults to `eye_color`.
+    pub eye_ball_color: Option<String>,
+    /// Shape of the finder patterns.
+    pub eye_style: EyeStyle,
+    /// Start and end colors of a linear gradient filling the dark modules, if any.
+    pub gradient: Option<(String, String)>,
+    /// Direction of `gradient` in degrees; 0 runs left to right.
+    pub gradient_angle: f64,
+    /// Fill the finder patterns with `gradient` too, instead of a solid color.
+    pub gradient_eyes: bool,
+    /// Image drawn behind the code in raster output. Light modules become transparent over it.
+    pub background_image: Option<PathBuf>,
+    /// Opacity (0 to 1) of the light color laid over `background_image`, to wash it out.
+    pub background_opacity: f64,
+    /// Lay out copies of the code in a grid on one sheet, for raster output.
+    pub tile: Option<Tiling>,
+    /// Foreground colors cycled through, one per frame, for animated GIF output.
+    pub animate_colors: Vec<String>,
+    /// How long each animation frame is shown, in milliseconds.
+    pub frame_delay_ms: u32,
+    /// The kind of code to generate. Styling beyond colors, label and frame only applies to QR
+    /// codes.
+    pub symbology: Symbology,
+    /// Prefer a Micro QR code when the payload fits in one.
+    pub micro: bool,
+    /// Force this QR version (1-40) instead of the smallest that fits.
+    pub version: Option<i16>,
+    /// Error correction level of regular QR codes. Micro codes pick their own.
+    pub ec_level: ErrorCorrection,
+    /// Output formats; a file is written for each.
+    pub formats: Vec<String>,
+    /// Further files to write, each with the format inferred from its extension.
+    pub extra_outputs: Vec<(PathBuf, String)>,
+    pub write: WriteOptions,
+}
+
+/// The command line's defaults, with an empty text payload and no output path.
+impl Default for QrCodeOptions {
+    fn default() -> Self {
+        Self {
+            payload: Payload::Text {
+                data: String::new(),
+            },
+            output_path: None,
+            dark_color: "#000000".to_string(),
+            light_color: "#ffffff".to_string(),
+            size: 512,
+            scale: None,
+            snap: false,
+            physical_size: None,
+            print_width_mm: None,
+            label: None,
+            label_size: None,
+            label_font: None,
+            frame_width: None,
+            frame_color: None,
+            module_shape: ModuleShape::Square,
+            module_radius: 0.25,
+            eye_color: None,
+            eye_ball_color: None,
+            eye_style: EyeStyle::Square,
+            gradient: None,
+            gradient_angle: 0.0,
+            gradient_eyes: false,
+            background_image: None,
+            background_opacity: 0.0,
+            tile: None,
+            animate_colors: Vec::new(),
+            frame_delay_ms: 500,
+            symbology: Symbology::Qr,
+            micro: false,
+            version: None,
+            ec_level: ErrorCorrection::H,
+            formats: vec![DEFAULT_FORMAT.to_string()],
+            extra_outputs: Vec::new(),
+            write: WriteOptions::default(),
+        }
+    }
+}
+
+impl QrCodeOptions {
+    /// Swap the foreground and background colors, e.g. for dark-mode displays.
+    pub fn invert_colors(&mut self) {
+        std::mem::swap(&mut self.dark_color, &mut self.light_color);
+    }
+
+    /// Check every color option, so a typo fails before anything is rendered instead of
+    /// producing a broken SVG.
+    pub fn validate_colors(&self) -> Result<(), Error> {
+        let optional = [&self.frame_color, &self.eye_color, &self.eye_ball_color];
+        let gradient = self.gradient.iter().flat_map(|(from, to)| [from, to]);
+        [&self.dark_color, &self.light_color]
+            .into_iter()
+            .chain(optional.into_iter().flatten())
+            .chain(gradient)
+            .chain(&self.animate_colors)
+            .try_for_each(|color| validate_hex_color(color).map_err(Error::InvalidColor))
+    }
+}
+
+/// Validate a hex color string (e.g. "#000000" or "#ffffff").
+pub fn validate_hex_color(color: &str) -> Result<(), String> {
+    if !color.starts_with('#') {
+        return Err(format!("Color must start with '#': {color}"));
+    }
+    let hex = &color[1..];
+    if hex.len() != 6 {
+        return Err(format!(
+            "Color must be 6 hex digits after '#': {color} (got {} digits)",
+            hex.len()
+        ));
+    }
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("Color contains invalid hex digits: {color}"));
+    }
+    Ok(())
+}
+
+/// Machine-readable description of a generated QR code, printed by `--json`.
+///
+/// Field names are part of the CLI's output contract; add new fields rather
+/// than renaming existing ones.
+#[derive(Debug, Serialize)]
+pub struct QrMetadata {
+    pub symbology: Symbology,
+    /// The QR version, or the number of layers of an Aztec code.
+    pub version: i16,
+    /// Whether this is a Micro QR code, or a compact Aztec code.
+    pub micro: bool,
+    /// The QR error correction level, or the share of error correction in an Aztec code.
+    pub ec_level: String,
+    /// Mask pattern chosen by the encoder: 0-7, or 0-3 for Micro QR codes. Aztec codes have none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mask: Option<u8>,
+    pub module_count: usize,
+    /// The encoded payload; for Wi-Fi codes this includes the password. Bytes that aren't valid
+    /// UTF-8 are shown as replacement characters.
+    pub payload: String,
+    /// Length of the encoded payload in bytes.
+    pub payload_length: usize,
+    pub output: Option<PathBuf>,
+    /// Size of the written image in bytes; absent when the code was only shown in the terminal.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<usize>,
     pub format: String,
-    pub overwrite: bool,
+    /// Every file written, one per requested format.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<SavedImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub print_dpi: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub module_size_mm: Option<f64>,
 }
 
-#[cfg(feature = "kitty_graphics")]
-pub fn print_qr_code_kitty(options: &QrCodeOptions) -> Result<(), Error> {
-    let contents_to_encode =
-        build_wifi_qr_payload(&options.ssid, &options.encryption, &options.password);
+impl QrMetadata {
+    fn new(
+        qrcode: &QrCode,
+        payload: &[u8],
+        files: Vec<SavedImage>,
+        options: &QrCodeOptions,
+    ) -> Self {
+        let (version, micro) = match qrcode.version() {
+            Version::Normal(v) => (v, false),
+            Version::Micro(v) => (v, true),
+        };
+        let print = print_resolution(
+            modules_with_quiet_zone(qrcode),
+            image_size(qrcode, options),
+            options,
+        );
+        Self {
+            symbology: Symbology::Qr,
+            version,
+            micro,
+            ec_level: format!("{:?}", qrcode.error_correction_level()),
+            mask: Some(mask_pattern(qrcode)),
+            module_count: qrcode.width(),
+            payload: String::from_utf8_lossy(payload).into_owned(),
+            payload_length: payload.len(),
+            output: files.first().map(|file| file.path.clone()),
+            bytes: files.first().map(|file| file.bytes),
+            format: options.formats.join(","),
+            files,
+            print_dpi: print.map(|p| p.dpi),
+            module_size_mm: print.map(|p| p.module_size_mm),
+        }
+    }
+
+    fn for_aztec(
+        code: &AztecCode,
+        payload: &[u8],
+        files: Vec<SavedImage>,
+        options: &QrCodeOptions,
+    ) -> Self {
+        let print = print_resolution(
+            code.modules_with_quiet_zone(),
+            aztec_image_size(code, options),
+            options,
+        );
+        Self {
+            symbology: Symbology::Aztec,
+            version: code.layers() as i16,
+            micro: code.compact(),
+            ec_level: format!("{}%", aztec::ec_percent(options.ec_level)),
+            mask: None,
+            module_count: code.width(),
+            payload: String::from_utf8_lossy(payload).into_owned(),
+            payload_length: payload.len(),
+            output: files.first().map(|file| file.path.clone()),
+            bytes: files.first().map(|file| file.bytes),
+            format: options.formats.join(","),
+            files,
+            print_dpi: print.map(|p| p.dpi),
+            module_size_mm: print.map(|p| p.module_size_mm),
+        }
+    }
+}
+
+/// Resolution DPI below which printed output is likely to look soft.
+const MIN_PRINT_DPI: f64 = 300.0;
+
+/// Smallest printed module edge that typical phone cameras decode reliably.
+const MIN_MODULE_SIZE_MM: f64 = 0.4;
+
+/// Effective resolution of the image when printed at `--print-width-mm`.
+#[derive(Debug, Clone, Copy)]
+struct PrintResolution {
+    dpi: f64,
+    module_size_mm: f64,
+}
+
+/// Print resolution of an image `pixels` wide showing `modules` modules across.
+fn print_resolution(modules: u32, pixels: u32, options: &QrCodeOptions) -> Option<PrintResolution> {
+    let width_mm = options.print_width_mm?;
+    let pixels = f64::from(pixels);
+    let modules = f64::from(modules);
+    Some(PrintResolution {
+        dpi: pixels / (width_mm / 25.4),
+        module_size_mm: width_mm / modules,
+    })
+}
+
+/// Log the print resolution and warn when it is too low for sharp or scannable output.
+fn check_print_resolution(modules: u32, pixels: u32, options: &QrCodeOptions) {
+    let Some(print) = print_resolution(modules, pixels, options) else {
+        return;
+    };
+    info!(
+        "Printed width gives {:.0} DPI and {:.2} mm per module",
+        print.dpi, print.module_size_mm
+    );
+    if print.dpi < MIN_PRINT_DPI {
+        warn!(
+            "Effective print resolution is {:.0} DPI, below the {MIN_PRINT_DPI} DPI recommended for \
+             print. Increase --size or reduce --print-width-mm.",
+            print.dpi
+        );
+    }
+    if print.module_size_mm < MIN_MODULE_SIZE_MM {
+        warn!(
+            "Printed modules are {:.2} mm wide, below the {MIN_MODULE_SIZE_MM} mm scanners need. \
+             Increase --print-width-mm or encode less data.",
+            print.module_size_mm
+        );
+    }
+}
+
+/// Encode the payload described by `options`, returning the QR code together with the bytes it
+/// was built from.
+fn encode_qr_code(options: &QrCodeOptions) -> Result<(QrCode, Vec<u8>), Error> {
+    options.validate_colors()?;
+    let data = options.payload.bytes();
+
+    let capacity = max_payload_bytes(options.ec_level);
+    if data.len() > capacity {
+        warn!(
+            "Payload is {} bytes, more than the {capacity} bytes a QR code holds at error \
+             correction {:?}.",
+            data.len(),
+            options.ec_level
+        );
+    }
+
+    let micro = if options.micro {
+        let micro = encode_micro(&data);
+        if micro.is_none() {
+            warn!(
+                "Payload of {} bytes doesn't fit in a Micro QR code; using a regular QR code instead.",
+                data.len()
+            );
+        }
+        micro
+    } else {
+        None
+    };
+
+    let qrcode = match (micro, options.version, options.ec_level.level()) {
+        (Some(qrcode), _, _) => qrcode,
+        (None, Some(version), ec_level) => encode_with_version(&data, version, ec_level)?,
+        (None, None, Some(ec_level)) => QrCode::with_error_correction_level(&data, ec_level)
+            .map_err(|e| Error::QrCode(format!("Failed to generate the QR code: {e}")))?,
+        (None, None, None) => {
+            let smallest = QrCode::with_error_correction_level(&data, EcLevel::L)
+                .map_err(|e| Error::QrCode(format!("Failed to generate the QR code: {e}")))?;
+            let qrcode = strongest_fitting(&data, smallest.version()).unwrap_or(smallest);
+            info!(
+                "Automatic error correction chose level {:?}",
+                qrcode.error_correction_level()
+            );
+            qrcode
+        }
+    };
+    info!(
+        "QR code generated successfully (version {:?}, error correction {:?}, mask {}).",
+        qrcode.version(),
+        qrcode.error_correction_level(),
+        mask_pattern(&qrcode)
+    );
+
+    check_image_size(modules_with_quiet_zone(&qrcode), options)?;
+
+    Ok((qrcode, data))
+}
+
+/// Encode `data` as a regular QR code of exactly `version`.
+///
+/// When the payload doesn't fit, the error names the smallest version that would hold it.
+fn encode_with_version(
+    data: &[u8],
+    version: i16,
+    ec_level: Option<EcLevel>,
+) -> Result<QrCode, Error> {
+    let encoded = match ec_level {
+        Some(ec_level) => QrCode::with_version(data, Version::Normal(version), ec_level).ok(),
+        None => strongest_fitting(data, Version::Normal(version)),
+    };
+    encoded.ok_or_else(|| {
+        // Auto falls back to level L, so report the smallest version at that level.
+        let ec_level = ec_level.unwrap_or(EcLevel::L);
+        match QrCode::with_error_correction_level(data, ec_level).map(|qr| qr.version()) {
+            Ok(Version::Normal(minimum)) if minimum > version => Error::PayloadTooLarge {
+                requested: version,
+                minimum,
+            },
+            _ => Error::QrCode(format!(
+                "Failed to generate a version {version} QR code at error correction {ec_level:?}"
+            )),
+        }
+    })
+}
+
+/// Read back the five data bits of the format information the encoder drew next to the top-left
+/// finder pattern: the error correction indicator (or Micro symbol number) followed by the mask.
+///
+/// The `qrcode` crate picks the mask pattern itself and has no way to force one, but the choice
+/// is recorded in these bits, at the positions and with the XOR masks of ISO/IEC 18004.
+fn format_data(qrcode: &QrCode) -> u16 {
+    const NORMAL_COORDS: [(usize, usize); 15] = [
+        (0, 8),
+        (1, 8),
+        (2, 8),
+        (3, 8),
+        (4, 8),
+        (5, 8),
+        (7, 8),
+        (8, 8),
+        (8, 7),
+        (8, 5),
+        (8, 4),
+        (8, 3),
+        (8, 2),
+        (8, 1),
+        (8, 0),
+    ];
+    const MICRO_COORDS: [(usize, usize); 15] = [
+        (1, 8),
+        (2, 8),
+        (3, 8),
+        (4, 8),
+        (5, 8),
+        (6, 8),
+        (7, 8),
+        (8, 8),
+        (8, 7),
+        (8, 6),
+        (8, 5),
+        (8, 4),
+        (8, 3),
+        (8, 2),
+        (8, 1),
+    ];
+    let (coords, xor) = match qrcode.version() {
+        Version::Normal(_) => (&NORMAL_COORDS, 0x5412),
+        Version::Micro(_) => (&MICRO_COORDS, 0x4445),
+    };
+    let raw = coords.iter().fold(0u16, |bits, &(x, y)| {
+        (bits << 1) | u16::from(qrcode[(x, y)] == Color::Dark)
+    });
+    (raw ^ xor) >> 10
+}
+
+/// The mask pattern the encoder chose for `qrcode`: 0-7, or 0-3 for Micro QR codes.
+fn mask_pattern(qrcode: &QrCode) -> u8 {
+    let mask_bits = match qrcode.version() {
+        Version::Normal(_) => 0b111,
+        Version::Micro(_) => 0b11,
+    };
+    (format_data(qrcode) & mask_bits) as u8
+}
+
+/// Encode `data` at `version` with the strongest error correction level it fits at.
+fn strongest_fitting(data: &[u8], version: Version) -> Option<QrCode> {
+    [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L]
+        .into_iter()
+        .find_map(|ec_level| QrCode::with_version(data, version, ec_level).ok())
+}
+
+/// Encode `data` as the smallest Micro QR code that holds it, preferring the strongest error
+/// correction that version supports. Returns `None` when even M4 is too small.
+fn encode_micro(data: &[u8]) -> Option<QrCode> {
+    (1..=4).find_map(|version| {
+        [EcLevel::Q, EcLevel::M, EcLevel::L]
+            .into_iter()
+            .find_map(|ec_level| QrCode::with_version(data, Version::Micro(version), ec_level).ok())
+    })
+}
+
+/// Width of the quiet zone around the code, in modules, as drawn by the `qrcode` renderers.
+///
+/// Micro QR codes only need half the quiet zone of regular ones.
+fn quiet_zone(qrcode: &QrCode) -> u32 {
+    if qrcode.version().is_micro() { 2 } else { 4 }
+}
+
+/// Edge length in pixels of the rendered image, including the quiet zone.
+///
+/// With a `scale`, every module (quiet zone included) is exactly `scale` pixels wide. With `snap`,
+/// `size` is rounded up to the next multiple of the module count.
+fn image_size(qrcode: &QrCode, options: &QrCodeOptions) -> u32 {
+    scaled_size(modules_with_quiet_zone(qrcode), options)
+}
+
+/// [`image_size`] of an Aztec code.
+fn aztec_image_size(code: &AztecCode, options: &QrCodeOptions) -> u32 {
+    scaled_size(code.modules_with_quiet_zone(), options)
+}
+
+/// Edge length in pixels of an image `modules` modules across, following `scale` and `snap`.
+///
+/// The size is checked by [`check_image_size`] when the code is encoded, so it fits in a `u32`
+/// here.
+fn scaled_size(modules: u32, options: &QrCodeOptions) -> u32 {
+    checked_scaled_size(modules, options).unwrap_or(u32::MAX)
+}
+
+/// [`scaled_size`], or `None` when it doesn't fit in a `u32`.
+fn checked_scaled_size(modules: u32, options: &QrCodeOptions) -> Option<u32> {
+    match options.scale {
+        Some(scale) => modules.checked_mul(scale),
+        None if options.snap => options.size.checked_next_multiple_of(modules),
+        // Renders round the canvas up to whole pixels per module, so that has to fit too.
+        None => options
+            .size
+            .checked_next_multiple_of(modules)
+            .map(|_| options.size),
+    }
+}
+
+/// Fail when an image `modules` modules across would be too large to describe, e.g. for a huge
+/// `--scale`, or a `--size` so close to the limit that rounding it up to whole modules overflows.
+fn check_image_size(modules: u32, options: &QrCodeOptions) -> Result<(), Error> {
+    if checked_scaled_size(modules, options).is_some() {
+        return Ok(());
+    }
+    let option = match options.scale {
+        Some(scale) => format!("--scale {scale}"),
+        None => format!("--size {}", options.size),
+    };
+    Err(Error::Image(format!(
+        "{option} is too large: the {modules} modules across the code wouldn't fit in an image \
+         {} pixels wide",
+        u32::MAX
+    )))
+}
 
-    let qrcode = QrCode::with_error_correction_level(contents_to_encode.as_bytes(), EcLevel::H)
-        .map_err(|e| Error::QrCode(format!("Failed to generate the QR code: {e}")))?;
-    info!("QR code generated successfully.");
+/// Label font size used when `--label-size` isn't given, scaled to the image width.
+fn default_label_size(size: u32) -> u32 {
+    (size / 14).max(12)
+}
+
+/// Number of modules across the rendered image, including the quiet zone on both sides.
+fn modules_with_quiet_zone(qrcode: &QrCode) -> u32 {
+    qrcode.width() as u32 + 2 * quiet_zone(qrcode)
+}
 
-    let image_svg = qrcode
+/// Render a QR code to an SVG document using the size, module shape, eye colors, gradient and
+/// background color from `options`.
+fn render_svg(qrcode: &QrCode, options: &QrCodeOptions, dark_color: &str) -> String {
+    if options.module_shape != ModuleShape::Square
+        || options.eye_style != EyeStyle::Square
+        || options.eye_color.is_some()
+        || options.eye_ball_color.is_some()
+        || options.gradient.is_some()
+    {
+        return render_shaped_svg(qrcode, options, dark_color);
+    }
+    qrcode
         .render()
-        .min_dimensions(options.size, options.size)
-        .dark_color(svg::Color(&options.dark_color))
+        .min_dimensions(image_size(qrcode, options), image_size(qrcode, options))
+        .dark_color(svg::Color(dark_color))
         .light_color(svg::Color(&options.light_color))
-        .build();
-    info!("QR code rendered to SVG.");
+        .build()
+}
 
-    let pixmap = load_svg(image_svg.as_bytes(), options.size)?;
-    let png_data = pixmap
-        .encode_png()
-        .map_err(|e| Error::Image(format!("Failed to encode PNG: {e}")))?;
-    info!("Encoded QR code to PNG.");
+/// Whether `options` only asks for plain square modules, which [`render_raster`] draws without
+/// the SVG round trip.
+fn is_plain(options: &QrCodeOptions) -> bool {
+    options.module_shape == ModuleShape::Square
+        && options.eye_style == EyeStyle::Square
+        && options.eye_color.is_none()
+        && options.eye_ball_color.is_none()
+        && options.gradient.is_none()
+        && options.label.is_none()
+        && options.frame_width.is_none()
+}
 
-    let action = Action::TransmitAndDisplay(
-        ActionTransmission {
-            format: Format::Png,
-            medium: Medium::Direct,
-            width: options.size,
-            height: options.size,
-            ..Default::default()
-        },
-        ActionPut {
-            move_cursor: true,
-            ..Default::default()
+/// Decode the finished `image` with `rqrr` and check that it holds `expected`, describing the
+/// problem otherwise. See-through pixels are read as if printed on white paper.
+fn read_back(image: &RgbaImage, expected: &[u8]) -> Result<(), &'static str> {
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(
+        image.width() as usize,
+        image.height() as usize,
+        |x, y| {
+            let [r, g, b, a] = image.get_pixel(x as u32, y as u32).0;
+            let luma = (u32::from(r) * 299 + u32::from(g) * 587 + u32::from(b) * 114) / 1000;
+            let alpha = u32::from(a);
+            ((luma * alpha + 255 * (255 - alpha)) / 255) as u8
         },
     );
+    let mut decoded = Vec::new();
+    match prepared.detect_grids().first() {
+        None => Err("no QR code could be found in it"),
+        Some(grid) if grid.decode_to(&mut decoded).is_err() => Err("it could not be decoded"),
+        Some(_) if decoded != expected => Err("it decodes to different contents"),
+        Some(_) => Ok(()),
+    }
+}
+
+/// Render the QR code in memory, as [`generate_qr_code`] would before writing it, and check
+/// that it decodes back to the payload.
+pub fn verify_qr_code(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+    // --micro falls back to a regular code when the payload doesn't fit, and that one can be read.
+    if qrcode.version().is_micro() {
+        return Err(Error::Unreadable(
+            "Micro QR codes can't be decoded for verification".to_string(),
+        ));
+    }
+    let veil = background_veil(options);
+    let raster = read_back_raster(&qrcode, &see_through(options), veil)?;
+    read_back(&raster, &payload).map_err(|problem| Error::Unreadable(problem.to_string()))?;
+
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
+}
+
+/// Render the image [`read_back`] checks, over the background image and `veil` if there is one.
+///
+/// `rqrr` locates finder patterns by their corners and can't find rounded ones that phone
+/// scanners read, so styled eyes are checked in their square form. Their size and position are
+/// the same either way.
+fn read_back_raster(
+    qrcode: &QrCode,
+    options: &QrCodeOptions,
+    veil: image::Rgba<u8>,
+) -> Result<RgbaImage, Error> {
+    let square_eyes;
+    let options = if options.eye_style == EyeStyle::Square {
+        options
+    } else {
+        square_eyes = QrCodeOptions {
+            eye_style: EyeStyle::Square,
+            ..options.clone()
+        };
+        &square_eyes
+    };
+    let (image, raster_width) = render_document(qrcode, options, &options.dark_color);
+    let raster = rasterize(qrcode, options, &image, raster_width)?;
+    match &options.background_image {
+        Some(background) => composite_over_background(&raster, background, veil),
+        None => Ok(raster),
+    }
+}
+
+/// `options` with see-through light modules when there is a background image to show through
+/// them.
+fn see_through(options: &QrCodeOptions) -> Cow<'_, QrCodeOptions> {
+    if options.background_image.is_none() {
+        return Cow::Borrowed(options);
+    }
+    Cow::Owned(QrCodeOptions {
+        light_color: "none".to_string(),
+        ..options.clone()
+    })
+}
+
+/// The light color at `background_opacity`, laid over a background image.
+fn background_veil(options: &QrCodeOptions) -> image::Rgba<u8> {
+    let [r, g, b, a] = rgba_pixel(&options.light_color);
+    let alpha = (f64::from(a) * options.background_opacity.clamp(0.0, 1.0)).round() as u8;
+    image::Rgba([r, g, b, alpha])
+}
+
+/// The pixel of a validated `#rrggbb` color, or transparent for `"none"`.
+fn rgba_pixel(color: &str) -> [u8; 4] {
+    if color == "none" {
+        return [0; 4];
+    }
+    let [r, g, b] = rgb_channels(color).map(|c| (c * 255.0).round() as u8);
+    [r, g, b, u8::MAX]
+}
+
+/// Draw a plain code (see [`is_plain`]) straight into an image, skipping the SVG round trip.
+///
+/// A pixel takes the color of the module under its center, which is how `resvg` fills the
+/// `crispEdges` document from [`render_svg`], so both give the same pixels.
+pub(crate) fn render_raster(
+    qrcode: &QrCode,
+    options: &QrCodeOptions,
+    dark_color: &str,
+) -> RgbaImage {
+    let size = image_size(qrcode, options);
+    let module_size = f64::from(size) / f64::from(modules_with_quiet_zone(qrcode));
+    let module_at: Vec<usize> = (0..size)
+        .map(|pixel| ((f64::from(pixel) + 0.5) / module_size) as usize)
+        .collect();
+
+    let colors = qrcode.to_colors();
+    let width = qrcode.width();
+    let quiet_zone = quiet_zone(qrcode) as usize;
+    let is_dark = |x: usize, y: usize| {
+        let (x, y) = (x.wrapping_sub(quiet_zone), y.wrapping_sub(quiet_zone));
+        x < width && y < width && colors[y * width + x] == Color::Dark
+    };
+    let (dark, light) = (rgba_pixel(dark_color), rgba_pixel(&options.light_color));
+
+    // Every pixel row within a module row is the same, so each is built once and copied.
+    let mut pixels = Vec::with_capacity(size as usize * size as usize * 4);
+    let mut row = Vec::new();
+    for (i, &y) in module_at.iter().enumerate() {
+        if i == 0 || module_at[i - 1] != y {
+            row = module_at
+                .iter()
+                .flat_map(|&x| if is_dark(x, y) { dark } else { light })
+                .collect();
+        }
+        pixels.extend_from_slice(&row);
+    }
+    RgbaImage::from_raw(size, size, pixels).expect("one pixel per module lookup")
+}
+
+/// Rasterize `document`, as returned by [`render_document`], `width` pixels wide. Plain codes
+/// are drawn directly instead.
+fn rasterize(
+    qrcode: &QrCode,
+    options: &QrCodeOptions,
+    document: &str,
+    width: u32,
+) -> Result<RgbaImage, Error> {
+    if is_plain(options) {
+        return Ok(render_raster(qrcode, options, &options.dark_color));
+    }
+    svg_to_rgba_image(document.as_bytes(), width)
+}
+
+/// Modules across one finder pattern, not counting its light separator.
+const FINDER_SIZE: usize = 7;
+
+/// The part of a finder pattern ("eye") a module belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FinderPart {
+    /// The outer 7x7 ring.
+    Frame,
+    /// The solid 3x3 center.
+    Ball,
+}
+
+/// Which part of a finder pattern the module at `x`, `y` (quiet zone excluded) belongs to, if any.
+fn finder_part(qrcode: &QrCode, x: usize, y: usize) -> Option<FinderPart> {
+    let far_start = qrcode.width() - FINDER_SIZE;
+    let local = |v: usize| match v {
+        v if v < FINDER_SIZE => Some(v),
+        // Micro QR codes only have the top-left finder.
+        v if v >= far_start && !qrcode.version().is_micro() => Some(v - far_start),
+        _ => None,
+    };
+    let (x, y) = match (local(x), local(y)) {
+        // The bottom-right corner has no finder.
+        (Some(lx), Some(ly)) if x < FINDER_SIZE || y < FINDER_SIZE => (lx, ly),
+        _ => return None,
+    };
+    let in_ball = |v: usize| (2..=4).contains(&v);
+    Some(if in_ball(x) && in_ball(y) {
+        FinderPart::Ball
+    } else {
+        FinderPart::Frame
+    })
+}
+
+/// Top-left module of each finder pattern (quiet zone excluded).
+fn finder_origins(qrcode: &QrCode) -> Vec<(usize, usize)> {
+    let far = qrcode.width() - FINDER_SIZE;
+    if qrcode.version().is_micro() {
+        vec![(0, 0)]
+    } else {
+        vec![(0, 0), (far, 0), (0, far)]
+    }
+}
+
+/// SVG path data for a `size`-pixel square at `x`, `y` with corners rounded by `radius`; a
+/// radius of half the size makes a circle.
+fn rounded_square(x: f64, y: f64, size: f64, radius: f64) -> String {
+    let side = size - 2.0 * radius;
+    format!(
+        "M{} {y}h{side}a{radius} {radius} 0 0 1 {radius} {radius}v{side}\
+         a{radius} {radius} 0 0 1 -{radius} {radius}h-{side}\
+         a{radius} {radius} 0 0 1 -{radius} -{radius}v-{side}\
+         a{radius} {radius} 0 0 1 {radius} -{radius}Z",
+        x + radius
+    )
+}
+
+/// Path data for the rings and centers of every finder pattern in `style`, which isn't
+/// [`EyeStyle::Square`]. Rings are an outer and an inner outline, to be filled with the even-odd
+/// rule.
+fn styled_eyes(qrcode: &QrCode, style: EyeStyle, quiet_zone: u32, unit: u32) -> (String, String) {
+    let unit = f64::from(unit);
+    // Corner radii for the 7-module ring outline, its 5-module hole and the 3-module center.
+    let [outer, inner, ball] = match style {
+        EyeStyle::Rounded => [2.0, 1.0, 0.75],
+        EyeStyle::Circle | EyeStyle::Square => [3.5, 2.5, 1.5],
+    }
+    .map(|radius| radius * unit);
+
+    let mut frames = String::new();
+    let mut balls = String::new();
+    for (x, y) in finder_origins(qrcode) {
+        let left = (x as f64 + f64::from(quiet_zone)) * unit;
+        let top = (y as f64 + f64::from(quiet_zone)) * unit;
+        frames.push_str(&rounded_square(left, top, 7.0 * unit, outer));
+        frames.push_str(&rounded_square(left + unit, top + unit, 5.0 * unit, inner));
+        balls.push_str(&rounded_square(
+            left + 2.0 * unit,
+            top + 2.0 * unit,
+            3.0 * unit,
+            ball,
+        ));
+    }
+    (frames, balls)
+}
+
+/// Render a QR code to SVG by drawing every module from the matrix, for module shapes, eye colors
+/// and gradients the `qrcode` renderer can't produce.
+///
+/// The layout matches [`render_svg`]: each module is a whole number of pixels and the canvas is
+/// at least [`image_size`] wide. Finder patterns ignore the module shape so scanners can still
+/// locate them; they follow the eye style and are drawn as separate paths so they can take their
+/// own colors.
+fn render_shaped_svg(qrcode: &QrCode, options: &QrCodeOptions, dark_color: &str) -> String {
+    let quiet_zone = quiet_zone(qrcode);
+    let modules = modules_with_quiet_zone(qrcode);
+    let unit = image_size(qrcode, options).div_ceil(modules);
+    let size = unit * modules;
+    let half = f64::from(unit) / 2.0;
+    let radius = f64::from(unit) * options.module_radius;
+    let (defs, dark) = match &options.gradient {
+        Some((from, to)) => (
+            linear_gradient(size, options.gradient_angle, from, to),
+            format!("url(#{GRADIENT_ID})"),
+        ),
+        None => (String::new(), dark_color.to_string()),
+    };
+    // Gradients on the finder patterns can hurt scanning, so they stay solid unless asked for.
+    let eye_default = if options.gradient_eyes {
+        dark.as_str()
+    } else {
+        dark_color
+    };
+    let eye = options.eye_color.as_deref().unwrap_or(eye_default);
+    let eye_ball = options.eye_ball_color.as_deref().unwrap_or(eye);
+
+    let mut eye_frames = String::new();
+    let mut eye_balls = String::new();
+    let mut data = String::new();
+    for y in 0..qrcode.width() {
+        for x in 0..qrcode.width() {
+            if qrcode[(x, y)] == Color::Light {
+                continue;
+            }
+            let left = (x as u32 + quiet_zone) * unit;
+            let top = (y as u32 + quiet_zone) * unit;
+            if let Some(part) = finder_part(qrcode, x, y) {
+                if options.eye_style != EyeStyle::Square {
+                    continue;
+                }
+                let path = match part {
+                    FinderPart::Frame => &mut eye_frames,
+                    FinderPart::Ball => &mut eye_balls,
+                };
+                let _ = write!(path, "M{left} {top}h{unit}v{unit}H{left}V{top}");
+                continue;
+            }
+            let _ = match options.module_shape {
+                ModuleShape::Square => write!(
+                    data,
+                    r#"<rect x="{left}" y="{top}" width="{unit}" height="{unit}"/>"#
+                ),
+                ModuleShape::Dots => write!(
+                    data,
+                    r#"<circle cx="{}" cy="{}" r="{half}"/>"#,
+                    f64::from(left) + half,
+                    f64::from(top) + half
+                ),
+                ModuleShape::Rounded => write!(
+                    data,
+                    r#"<rect x="{left}" y="{top}" width="{unit}" height="{unit}" rx="{radius}"/>"#
+                ),
+            };
+        }
+    }
+    let eye_shape = if options.eye_style == EyeStyle::Square {
+        r#"shape-rendering="crispEdges""#
+    } else {
+        (eye_frames, eye_balls) = styled_eyes(qrcode, options.eye_style, quiet_zone, unit);
+        r#"fill-rule="evenodd""#
+    };
+
+    format!(
+        concat!(
+            r#"<?xml version="1.0" standalone="yes"?>"#,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" version="1.1" width="{size}" height="{size}" viewBox="0 0 {size} {size}">"#,
+            "{defs}",
+            r#"<rect x="0" y="0" width="{size}" height="{size}" fill="{light}"/>"#,
+            r#"<path fill="{eye}" {eye_shape} d="{eye_frames}"/>"#,
+            r#"<path fill="{eye_ball}" {eye_shape} d="{eye_balls}"/>"#,
+            r#"<g fill="{dark}">{data}</g>"#,
+            "</svg>"
+        ),
+        size = size,
+        defs = defs,
+        light = options.light_color,
+        dark = dark,
+        eye = eye,
+        eye_ball = eye_ball,
+        eye_shape = eye_shape,
+        eye_frames = eye_frames,
+        eye_balls = eye_balls,
+        data = data,
+    )
+}
+
+/// Element id of the foreground gradient in SVGs built by [`render_shaped_svg`].
+const GRADIENT_ID: &str = "foreground-gradient";
+
+/// A `<defs>` block with a linear gradient from `from` to `to` across a `size`-pixel canvas.
+///
+/// An `angle` of 0 runs left to right; positive angles rotate clockwise. The gradient uses canvas
+/// coordinates so it spans the whole code instead of restarting in every module.
+fn linear_gradient(size: u32, angle: f64, from: &str, to: &str) -> String {
+    let center = f64::from(size) / 2.0;
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (dx, dy) = (cos * center, sin * center);
+    format!(
+        concat!(
+            r#"<defs><linearGradient id="{id}" gradientUnits="userSpaceOnUse" "#,
+            r#"x1="{x1:.2}" y1="{y1:.2}" x2="{x2:.2}" y2="{y2:.2}">"#,
+            r#"<stop offset="0" stop-color="{from}"/><stop offset="1" stop-color="{to}"/>"#,
+            "</linearGradient></defs>"
+        ),
+        id = GRADIENT_ID,
+        x1 = center - dx,
+        y1 = center - dy,
+        x2 = center + dx,
+        y2 = center + dy,
+        from = from,
+        to = to,
+    )
+}
+
+/// The red, green and blue channels of a validated `#rrggbb` color, each from 0 to 1.
+fn rgb_channels(color: &str) -> [f64; 3] {
+    let channel = |i: usize| {
+        let value = color
+            .get(1 + 2 * i..3 + 2 * i)
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            .unwrap_or(0);
+        f64::from(value) / 255.0
+    };
+    [channel(0), channel(1), channel(2)]
+}
+
+/// Format a validated `#rrggbb` color as PostScript's 0-1 RGB components.
+fn postscript_rgb(color: &str) -> String {
+    let [r, g, b] = rgb_channels(color);
+    format!("{r:.4} {g:.4} {b:.4}")
+}
+
+/// Contrast ratio below which scanners start to struggle to tell dark modules from light ones.
+const MIN_CONTRAST_RATIO: f64 = 4.5;
+
+/// WCAG contrast ratio between two `#rrggbb` colors, from 1 (identical) to 21 (black on white).
+fn contrast_ratio(a: &str, b: &str) -> f64 {
+    let luminance = |color: &str| {
+        let [r, g, b] = rgb_channels(color).map(|c| {
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        });
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    };
+    let (a, b) = (luminance(a), luminance(b));
+    (a.max(b) + 0.05) / (a.min(b) + 0.05)
+}
+
+/// Render a QR code as Encapsulated PostScript, drawing dark modules with `rectfill`.
+///
+/// The bounding box is in points: `--physical-size` when given, otherwise one point per pixel of
+/// [`image_size`] (72 DPI). Labels, frames, module shapes, eye styles and colors and gradients
+/// aren't drawn.
+fn render_eps(qrcode: &QrCode, options: &QrCodeOptions) -> String {
+    if options.label.is_some()
+        || options.frame_width.is_some()
+        || options.module_shape != ModuleShape::Square
+        || options.eye_style != EyeStyle::Square
+        || options.eye_color.is_some()
+        || options.eye_ball_color.is_some()
+        || options.gradient.is_some()
+    {
+        warn!(
+            "EPS output only contains the QR code; label, frame, module shape, eye style and \
+             colors and gradient are ignored."
+        );
+    }
+
+    let quiet_zone = quiet_zone(qrcode) as usize;
+    let modules = modules_with_quiet_zone(qrcode);
+    let points = options
+        .physical_size
+        .map_or(f64::from(image_size(qrcode, options)), |inches| {
+            inches * 72.0
+        });
+    let scale = points / f64::from(modules);
+
+    let mut eps = String::new();
+    let _ = writeln!(eps, "%!PS-Adobe-3.0 EPSF-3.0");
+    let _ = writeln!(eps, "%%BoundingBox: 0 0 {0} {0}", points.ceil());
+    let _ = writeln!(eps, "%%HiResBoundingBox: 0 0 {points:.4} {points:.4}");
+    let _ = writeln!(eps, "%%Creator: ciphercanvas");
+    let _ = writeln!(eps, "%%EndComments");
+    let _ = writeln!(eps, "{scale:.6} {scale:.6} scale");
+    let _ = writeln!(eps, "{} setrgbcolor", postscript_rgb(&options.light_color));
+    let _ = writeln!(eps, "0 0 {modules} {modules} rectfill");
+    let _ = writeln!(eps, "{} setrgbcolor", postscript_rgb(&options.dark_color));
+
+    // PostScript's origin is the bottom-left corner; draw each horizontal run of dark modules as
+    // a single rectangle.
+    let width = qrcode.width();
+    for y in 0..width {
+        let row = modules as usize - quiet_zone - y - 1;
+        let mut x = 0;
+        while x < width {
+            if qrcode[(x, y)] == Color::Light {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < width && qrcode[(x, y)] == Color::Dark {
+                x += 1;
+            }
+            let _ = writeln!(eps, "{} {row} {} 1 rectfill", start + quiet_zone, x - start);
+        }
+    }
+
+    let _ = writeln!(eps, "showpage");
+    let _ = writeln!(eps, "%%EOF");
+    eps
+}
+
+/// Warn when a text rendering `columns` characters wide is wider than the terminal, since
+/// wrapped lines make the code unscannable. Nothing is checked when stdout isn't a terminal.
+fn check_terminal_width(columns: u32, ascii: bool) {
+    let available = terminal_size::terminal_size().map(|(Width(width), _)| width);
+    if let Some(message) = terminal_width_warning(columns, available, ascii) {
+        warn!("{message}");
+    }
+}
+
+/// The warning for [`check_terminal_width`], if the code doesn't fit in `available` columns.
+fn terminal_width_warning(columns: u32, available: Option<u16>, ascii: bool) -> Option<String> {
+    let available = available.filter(|&width| columns > u32::from(width))?;
+    let hint = if ascii && columns / 2 <= u32::from(available) {
+        "Use --terminal unicode, which needs half the width, or write it to a file with --output."
+    } else {
+        "Widen the terminal or write it to a file with --output."
+    };
+    Some(format!(
+        "The QR code needs {columns} columns but the terminal has {available}, so its lines will \
+         wrap and it won't scan. {hint}"
+    ))
+}
 
-    let mut command = Command::new(action);
-    command.payload = std::borrow::Cow::Borrowed(&png_data);
+/// Render a QR code as plain text, using `#` for dark modules and spaces for light ones.
+///
+/// Each module is two characters wide so it looks square in a typical monospace font, and the
+/// quiet zone is kept so the result stays scannable.
+fn render_ascii(qrcode: &QrCode) -> String {
+    qrcode
+        .render::<char>()
+        .dark_color('#')
+        .light_color(' ')
+        .module_dimensions(2, 1)
+        .build()
+}
+
+/// Print the QR code to stdout as plain ASCII text, for places that can't show images or
+/// block glyphs.
+pub fn print_qr_code_ascii(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+
+    check_terminal_width(2 * modules_with_quiet_zone(&qrcode), true);
+
+    println!("{}", render_ascii(&qrcode));
+    info!("Printed QR code to terminal as ASCII text.");
+
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
+}
+
+/// Print the QR code to stdout with Unicode half-block characters, two modules per cell.
+///
+/// Colors are inverted so the code reads correctly on the usual light-on-dark terminal.
+pub fn print_qr_code_unicode(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+
+    check_terminal_width(modules_with_quiet_zone(&qrcode), false);
+
+    let text = qrcode
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .build();
+    println!("{text}");
+    info!("Printed QR code to terminal using Unicode blocks.");
+
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
+}
+
+/// Rasterize a QR code to PNG bytes for the terminal graphics protocols, along with the image's
+/// width and height in pixels.
+#[cfg(any(feature = "kitty_graphics", feature = "iterm2_graphics"))]
+fn render_png(qrcode: &QrCode, options: &QrCodeOptions) -> Result<(Vec<u8>, u32, u32), Error> {
+    let image = if is_plain(options) {
+        render_raster(qrcode, options, &options.dark_color)
+    } else {
+        let image_svg = render_svg(qrcode, options, &options.dark_color);
+        svg_to_rgba_image(image_svg.as_bytes(), image_size(qrcode, options))?
+    };
+    info!("QR code rendered to image.");
+
+    let png_data = encode_rgba(&image, image::ImageFormat::Png)?;
+    info!("Encoded QR code to PNG.");
+
+    Ok((png_data, image.width(), image.height()))
+}
+
+/// Copy the QR code to the system clipboard: the SVG text for the `svg` format, otherwise the
+/// rendered image.
+///
+/// On Linux the clipboard is served by this process, so the contents only outlive it when a
+/// clipboard manager picks them up.
+#[cfg(feature = "clipboard")]
+pub fn copy_qr_code_to_clipboard(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+    let (image, raster_width) = render_document(&qrcode, options, &options.dark_color);
+
+    let clipboard_error = |e: arboard::Error| Error::Clipboard(e.to_string());
+    let mut clipboard = Clipboard::new().map_err(clipboard_error)?;
+    let format = &options.formats[0];
+    if format == "svg" {
+        clipboard.set_text(image).map_err(clipboard_error)?;
+    } else {
+        let rgba = rasterize(&qrcode, options, &image, raster_width)?;
+        clipboard
+            .set_image(arboard::ImageData {
+                width: rgba.width() as usize,
+                height: rgba.height() as usize,
+                bytes: Cow::Owned(rgba.into_raw()),
+            })
+            .map_err(clipboard_error)?;
+    }
+    info!("Copied QR code to the clipboard as {format}");
+
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
+}
+
+/// Print the QR code using iTerm2's inline image escape sequence (`OSC 1337 ; File=...`).
+#[cfg(feature = "iterm2_graphics")]
+pub fn print_qr_code_iterm2(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+    let (png_data, width, height) = render_png(&qrcode, options)?;
+
+    let mut stdout = std::io::stdout().lock();
+    write!(
+        stdout,
+        "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=1:{}\x07",
+        png_data.len(),
+        width,
+        height,
+        BASE64.encode(&png_data)
+    )?;
+    writeln!(stdout)?;
+    stdout.flush()?;
+
+    info!("Printed QR code to terminal using the iTerm2 inline image protocol.");
+
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
+}
+
+/// Base64 bytes sent per Kitty graphics escape sequence. The protocol caps chunks at 4096 bytes
+/// and needs every chunk but the last to be a multiple of 4.
+#[cfg(feature = "kitty_graphics")]
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+/// The number of terminal columns a Kitty image spans when `--cells` isn't given: as wide as the
+/// Unicode rendering (one column per module), shrunk to fit the terminal. Cells are about twice
+/// as tall as they are wide, so the code fits vertically when it spans at most twice the rows.
+#[cfg(feature = "kitty_graphics")]
+fn kitty_auto_cells(modules: u32, terminal: Option<(u16, u16)>) -> u32 {
+    match terminal {
+        Some((columns, rows)) => modules
+            .min(u32::from(columns))
+            .min(u32::from(rows.saturating_sub(1)) * 2)
+            .max(1),
+        None => modules,
+    }
+}
+
+/// Split a PNG into Kitty graphics escape sequences that transmit and display it. Only the first
+/// chunk carries the control data: the pixel size (`s=`, `v=`) used for placement, and the
+/// number of columns (`c=`) to scale the image to. Kitty picks the rows to keep it square.
+#[cfg(feature = "kitty_graphics")]
+fn kitty_escape_sequences(png_data: &[u8], width: u32, height: u32, columns: u32) -> Vec<String> {
+    let encoded = BASE64.encode(png_data);
+    let chunks: Vec<&str> = encoded
+        .as_bytes()
+        .chunks(KITTY_CHUNK_SIZE)
+        .map(|chunk| std::str::from_utf8(chunk).expect("base64 is ASCII"))
+        .collect();
+    let last = chunks.len().saturating_sub(1);
+
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| {
+            let more = u8::from(i < last);
+            if i == 0 {
+                format!("\x1b_Ga=T,f=100,s={width},v={height},c={columns},m={more};{chunk}\x1b\\")
+            } else {
+                format!("\x1b_Gm={more};{chunk}\x1b\\")
+            }
+        })
+        .collect()
+}
+
+/// Print the QR code with the Kitty graphics protocol, `cells` terminal columns wide (see
+/// [`kitty_auto_cells`] for the default).
+#[cfg(feature = "kitty_graphics")]
+pub fn print_qr_code_kitty(
+    options: &QrCodeOptions,
+    cells: Option<u32>,
+) -> Result<QrMetadata, Error> {
+    let (qrcode, payload) = encode_qr_code(options)?;
+
+    let (png_data, width, height) = render_png(&qrcode, options)?;
+    let columns = cells.unwrap_or_else(|| {
+        let terminal = terminal_size::terminal_size()
+            .map(|(Width(columns), terminal_size::Height(rows))| (columns, rows));
+        kitty_auto_cells(modules_with_quiet_zone(&qrcode), terminal)
+    });
 
-    let wrapped = WrappedCommand::new(command);
     let mut stdout = std::io::stdout().lock();
-    wrapped
-        .send_chunked(&mut stdout)
-        .map_err(|e| Error::Image(format!("Failed to send to kitty: {}", e)))?;
+    for sequence in kitty_escape_sequences(&png_data, width, height, columns) {
+        stdout.write_all(sequence.as_bytes())?;
+    }
+    writeln!(stdout)?;
     stdout.flush()?;
-    println!();
 
     info!("Printed QR code to terminal using Kitty graphics protocol.");
 
-    Ok(())
+    Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options))
 }
 
-pub fn generate_qr_code(options: &QrCodeOptions) -> Result<(), Error> {
-    if options.size < 256 {
+pub fn generate_qr_code(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    if options.scale.is_none() && options.size < 256 {
         warn!("Image size is lower than 256. The resulting QR code may appear cropped.");
     }
+    if options.symbology == Symbology::Aztec {
+        return generate_aztec_code(options);
+    }
 
-    let contents_to_encode =
-        build_wifi_qr_payload(&options.ssid, &options.encryption, &options.password);
+    let (qrcode, payload) = encode_qr_code(options)?;
 
-    let qrcode = QrCode::with_error_correction_level(contents_to_encode.as_bytes(), EcLevel::H)
-        .map_err(|e| Error::QrCode(format!("Failed to generate the QR code: {e}")))?;
-    info!("QR code generated successfully.");
+    let modules = modules_with_quiet_zone(&qrcode);
+    // Most sizes, the default included, aren't a whole number of modules, and that scans fine,
+    // so this is only a hint.
+    // encode_qr_code already checked that the rounded-up size fits.
+    let snapped = options.size.next_multiple_of(modules);
+    if options.scale.is_none() && !options.snap && snapped != options.size {
+        info!(
+            "Image size {} isn't a multiple of the {modules} modules across the QR code, so module \
+             edges won't line up with pixels. Use --snap to round the size up to {snapped}.",
+            options.size,
+        );
+    }
 
-    let image = qrcode
-        .render()
-        .min_dimensions(options.size, options.size)
-        .dark_color(svg::Color(&options.dark_color))
-        .light_color(svg::Color(&options.light_color))
-        .build();
+    check_print_resolution(
+        modules_with_quiet_zone(&qrcode),
+        image_size(&qrcode, options),
+        options,
+    );
+
+    // A background image only shows through if the light modules are see-through.
+    if options.background_image.is_some() {
+        warn!(
+            "A background image can make the code much harder to scan. Keep it subtle and light, \
+             and test the result with several phones."
+        );
+    }
+    let veil = background_veil(options);
+    let options = &*see_through(options);
 
+    let (image, raster_width) = render_document(&qrcode, options, &options.dark_color);
     info!("QR code rendered to image.");
 
-    if let Some(path) = &options.output_path {
-        save_image(
-            path,
-            &options.format,
-            &image,
-            options.size,
-            options.overwrite,
-        )?;
+    let Some(path) = &options.output_path else {
+        println!("{image}");
+        return Ok(QrMetadata::new(&qrcode, &payload, Vec::new(), options));
+    };
+
+    let needs_raster = options
+        .formats
+        .iter()
+        .chain(options.extra_outputs.iter().map(|(_, format)| format))
+        .any(|format| raster_format(format).is_some());
+    // Styling can cost more error correction than the code has, so styled codes are decoded
+    // again as a check. The decoder doesn't read Micro QR codes.
+    let styled =
+        !qrcode.version().is_micro() && (!is_plain(options) || options.background_image.is_some());
+    let rendered = if needs_raster || styled {
+        let mut raster = rasterize(&qrcode, options, &image, raster_width)?;
+        if let Some(background) = &options.background_image {
+            raster = composite_over_background(&raster, background, veil)?;
+        }
+        let checked = match options.eye_style {
+            _ if !styled => Ok(()),
+            EyeStyle::Square => read_back(&raster, &payload),
+            _ => read_back(&read_back_raster(&qrcode, options, veil)?, &payload),
+        };
+        if let Err(problem) = checked {
+            warn!(
+                "The rendered code failed a read-back check: {problem}. Tone down the styling \
+                 (module shape, colors, background image) and test it with a phone."
+            );
+        }
+        if let Some(tiling) = options.tile {
+            let background = image::Rgba(rgba_pixel(&options.light_color));
+            raster = tile_image(&raster, tiling, background)?;
+        }
+        RenderedImage::with_raster(&image, raster)
     } else {
+        RenderedImage::new(&image, raster_width)
+    };
+
+    // Every file is encoded from the same rendering. Animated GIFs and EPS aren't drawn from the
+    // rendered document, so they are handled here.
+    let multiple = options.formats.len() > 1;
+    let targets = options
+        .formats
+        .iter()
+        .map(|format| (output_path_for_format(path, format, multiple), format))
+        .chain(
+            options
+                .extra_outputs
+                .iter()
+                .map(|(path, format)| (path.clone(), format)),
+        );
+    let mut written = Vec::new();
+    for (target, format) in targets {
+        let bytes = match format.as_str() {
+            "gif" if !options.animate_colors.is_empty() => {
+                encode_animation(&qrcode, options, raster_width)?
+            }
+            "eps" => render_eps(&qrcode, options).into_bytes(),
+            _ => {
+                written.extend(save_image(
+                    &target,
+                    std::slice::from_ref(format),
+                    &rendered,
+                    options.write,
+                )?);
+                continue;
+            }
+        };
+        written.push(write_image(&target, format, &bytes, options.write)?);
+    }
+
+    Ok(QrMetadata::new(&qrcode, &payload, written, options))
+}
+
+/// Generate an Aztec code to `options.output_path`. It shares the label, frame and output steps
+/// with QR codes; the QR-only styling options are rejected before getting here.
+fn generate_aztec_code(options: &QrCodeOptions) -> Result<QrMetadata, Error> {
+    options.validate_colors()?;
+    let payload = options.payload.bytes();
+    let code = AztecCode::encode(&payload, options.ec_level)?;
+    check_image_size(code.modules_with_quiet_zone(), options)?;
+    let size = aztec_image_size(&code, options);
+    check_print_resolution(code.modules_with_quiet_zone(), size, options);
+
+    let svg = code.render_svg(size, &options.dark_color, &options.light_color);
+    let (image, raster_width) = decorate_document(svg, size, options);
+    info!("Aztec code rendered to image.");
+
+    let Some(path) = &options.output_path else {
         println!("{image}");
+        return Ok(QrMetadata::for_aztec(&code, &payload, Vec::new(), options));
+    };
+    let rendered = RenderedImage::new(&image, raster_width);
+    let mut written = save_image(path, &options.formats, &rendered, options.write)?;
+    for (target, format) in &options.extra_outputs {
+        written.extend(save_image(
+            target,
+            std::slice::from_ref(format),
+            &rendered,
+            options.write,
+        )?);
     }
-    Ok(())
+    Ok(QrMetadata::for_aztec(&code, &payload, written, options))
+}
+
+/// Encode an animated GIF with one frame per color in `options.animate_colors`, warning about
+/// colors with too little contrast to scan.
+fn encode_animation(
+    qrcode: &QrCode,
+    options: &QrCodeOptions,
+    raster_width: u32,
+) -> Result<Vec<u8>, Error> {
+    for color in &options.animate_colors {
+        let ratio = contrast_ratio(color, &options.light_color);
+        if ratio < MIN_CONTRAST_RATIO {
+            warn!(
+                "Animation color {color} has a contrast ratio of {ratio:.1} against the \
+                 background; frames below {MIN_CONTRAST_RATIO} may not scan."
+            );
+        }
+    }
+    let frames: Vec<String> = options
+        .animate_colors
+        .iter()
+        .map(|color| render_document(qrcode, options, color).0)
+        .collect();
+    encode_gif(&frames, raster_width, options.frame_delay_ms)
 }
 
-/// Build the standard Wi-Fi QR code payload string.
+/// Render the full SVG document: the code with `dark_color` modules, plus any label, frame and
+/// physical size from `options`.
 ///
-/// Format: `WIFI:S:<ssid>;T:<encryption>;P:<password>;;`
-/// See: <https://github.com/zxing/zxing/wiki/Barcode-Contents#wi-fi-network-config-android-ios-11>
-fn escape_wifi_value(input: &str) -> String {
-    let mut out = String::with_capacity(input.len());
-    for c in input.chars() {
-        match c {
-            '\\' => out.push_str("\\\\"),
-            ';' => out.push_str("\\;"),
-            ',' => out.push_str("\\,"),
-            ':' => out.push_str("\\:"),
-            _ => out.push(c),
-        }
-    }
-    out
-}
-
-fn build_wifi_qr_payload(ssid: &str, encryption: &str, password: &str) -> String {
-    let ssid_escaped = escape_wifi_value(ssid);
-    let password_escaped = escape_wifi_value(password);
-    let encryption_escaped = escape_wifi_value(&encryption.to_uppercase());
-    format!(
-        "WIFI:S:{};T:{};P:{};;",
-        ssid_escaped, encryption_escaped, password_escaped
-    )
+/// Also returns the raster width that keeps the code itself at [`image_size`] pixels, since the
+/// label and frame grow the drawing.
+pub(crate) fn render_document(
+    qrcode: &QrCode,
+    options: &QrCodeOptions,
+    dark_color: &str,
+) -> (String, u32) {
+    let size = image_size(qrcode, options);
+    decorate_document(render_svg(qrcode, options, dark_color), size, options)
+}
+
+/// Add the label, frame and physical size from `options` to `image`, a code drawn `size` pixels
+/// wide, and return it with the raster width that keeps the code at that size.
+fn decorate_document(mut image: String, size: u32, options: &QrCodeOptions) -> (String, u32) {
+    let qr_width = svg_doc::view_box_width(&image);
+    if let Some(label) = &options.label {
+        let font_size = options
+            .label_size
+            .unwrap_or_else(|| default_label_size(size));
+        image = svg_doc::add_label(
+            &image,
+            label,
+            font_size,
+            options.label_font.as_deref(),
+            &options.dark_color,
+            &options.light_color,
+        );
+    }
+    if let Some(frame_width) = options.frame_width {
+        let color = options
+            .frame_color
+            .as_deref()
+            .unwrap_or(&options.dark_color);
+        image = svg_doc::add_frame(&image, frame_width, color);
+    }
+    let raster_width = match (qr_width, svg_doc::view_box_width(&image)) {
+        (Some(qr_width), Some(width)) => (f64::from(size) * width / qr_width).round() as u32,
+        _ => size,
+    };
+    if let Some(inches) = options
+        .physical_size
+        .filter(|_| options.formats.iter().any(|format| format == "svg"))
+    {
+        image = svg_doc::set_physical_size(&image, inches);
+    }
+    (image, raster_width)
 }
 
 #[cfg(test)]
@@ -144,44 +1569,606 @@ mod tests {
     use super::*;
 
     #[test]
-    fn wifi_qr_format_basic() {
-        let payload = build_wifi_qr_payload("MyNetwork", "WPA", "secret123");
-        assert_eq!(payload, "WIFI:S:MyNetwork;T:WPA;P:secret123;;");
+    fn terminal_width_warning_suggests_a_fit() {
+        assert_eq!(terminal_width_warning(58, Some(80), true), None);
+        assert_eq!(terminal_width_warning(58, None, true), None);
+        let ascii = terminal_width_warning(98, Some(80), true).unwrap();
+        assert!(ascii.contains("needs 98 columns but the terminal has 80"));
+        assert!(ascii.contains("--terminal unicode"));
+        let unicode = terminal_width_warning(98, Some(80), false).unwrap();
+        assert!(!unicode.contains("--terminal unicode"));
+    }
+
+    #[test]
+    fn read_back_check_catches_unreadable_codes() {
+        let options = wifi_options();
+        let (qrcode, payload) = encode_qr_code(&options).unwrap();
+        let image = render_raster(&qrcode, &options, &options.dark_color);
+        assert_eq!(read_back(&image, &payload), Ok(()));
+        assert_eq!(
+            read_back(&image, b"something else"),
+            Err("it decodes to different contents")
+        );
+
+        let blank = RgbaImage::from_pixel(64, 64, image::Rgba([255; 4]));
+        assert!(read_back(&blank, &payload).is_err());
+    }
+
+    #[test]
+    fn verification_decodes_styled_codes() {
+        let options = QrCodeOptions {
+            module_shape: ModuleShape::Dots,
+            ..wifi_options()
+        };
+        assert!(verify_qr_code(&options).is_ok());
+
+        let faint = QrCodeOptions {
+            dark_color: "#f4f4f4".to_string(),
+            ..options
+        };
+        assert!(matches!(verify_qr_code(&faint), Err(Error::Unreadable(_))));
+    }
+
+    #[test]
+    fn verification_reads_a_micro_fallback() {
+        let options = QrCodeOptions {
+            micro: true,
+            ..wifi_options()
+        };
+        assert!(verify_qr_code(&options).is_ok());
+
+        let micro = QrCodeOptions {
+            payload: Payload::Text {
+                data: "12345".to_string(),
+            },
+            ..options
+        };
+        assert!(matches!(verify_qr_code(&micro), Err(Error::Unreadable(_))));
     }
 
+    #[cfg(feature = "kitty_graphics")]
     #[test]
-    fn wifi_qr_format_none_encryption() {
-        let payload = build_wifi_qr_payload("GuestWifi", "None", "nopass");
-        assert_eq!(payload, "WIFI:S:GuestWifi;T:NONE;P:nopass;;");
+    fn kitty_chunks_carry_the_size_up_front() {
+        let png_data = vec![0u8; KITTY_CHUNK_SIZE];
+        let sequences = kitty_escape_sequences(&png_data, 300, 200, 20);
+        assert_eq!(sequences.len(), 2);
+        assert!(sequences[0].starts_with("\x1b_Ga=T,f=100,s=300,v=200,c=20,m=1;"));
+        assert!(sequences[1].starts_with("\x1b_Gm=0;"));
+
+        let payload_len = |sequence: &str| {
+            let start = sequence.find(';').unwrap() + 1;
+            sequence.len() - start - "\x1b\\".len()
+        };
+        assert_eq!(payload_len(&sequences[0]), KITTY_CHUNK_SIZE);
+        assert_eq!(payload_len(&sequences[1]) % 4, 0);
+
+        let single = kitty_escape_sequences(&[1, 2, 3], 10, 10, 5);
+        assert_eq!(single, ["\x1b_Ga=T,f=100,s=10,v=10,c=5,m=0;AQID\x1b\\"]);
     }
 
+    #[cfg(feature = "kitty_graphics")]
     #[test]
-    fn wifi_qr_format_lowercase_encryption_uppercased() {
-        let payload = build_wifi_qr_payload("Home", "wpa", "password");
-        assert_eq!(payload, "WIFI:S:Home;T:WPA;P:password;;");
+    fn kitty_auto_cells_fit_the_terminal() {
+        assert_eq!(kitty_auto_cells(29, None), 29);
+        assert_eq!(kitty_auto_cells(29, Some((80, 24))), 29);
+        assert_eq!(kitty_auto_cells(57, Some((40, 50))), 40);
+        assert_eq!(kitty_auto_cells(57, Some((200, 21))), 40);
     }
 
     #[test]
-    fn wifi_qr_format_wep() {
-        let payload = build_wifi_qr_payload("OldNetwork", "WEP", "wepkey");
-        assert_eq!(payload, "WIFI:S:OldNetwork;T:WEP;P:wepkey;;");
+    fn ascii_rendering_uses_two_columns_per_module() {
+        let qrcode = QrCode::new(b"ciphercanvas").unwrap();
+        let ascii = render_ascii(&qrcode);
+        let modules_with_quiet_zone = qrcode.width() + 8;
+
+        let lines: Vec<&str> = ascii.lines().collect();
+        assert_eq!(lines.len(), modules_with_quiet_zone);
+        assert!(
+            lines
+                .iter()
+                .all(|line| line.len() == modules_with_quiet_zone * 2)
+        );
+        assert!(ascii.chars().all(|c| matches!(c, '#' | ' ' | '\n')));
+    }
+
+    fn wifi_options() -> QrCodeOptions {
+        QrCodeOptions {
+            payload: Payload::Wifi {
+                ssid: "MyNetwork".to_string(),
+                encryption: "WPA".to_string(),
+                password: "secret123".to_string(),
+            },
+            formats: vec!["svg".to_string()],
+            write: WriteOptions {
+                create_dirs: true,
+                ..WriteOptions::default()
+            },
+            ..QrCodeOptions::default()
+        }
+    }
+
+    thread_local! {
+        static WARNINGS: std::cell::RefCell<Vec<String>> = const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    /// Records warnings logged on the current thread, so tests running in parallel don't see
+    /// each other's.
+    struct CaptureWarnings;
+
+    impl log::Log for CaptureWarnings {
+        fn enabled(&self, metadata: &log::Metadata) -> bool {
+            metadata.level() <= log::Level::Warn
+        }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                WARNINGS.with(|warnings| warnings.borrow_mut().push(record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    /// Run `f` and return the warnings it logged.
+    fn warnings_from(f: impl FnOnce()) -> Vec<String> {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            log::set_logger(&CaptureWarnings).unwrap();
+            log::set_max_level(log::LevelFilter::Warn);
+        });
+        WARNINGS.with(|warnings| warnings.borrow_mut().clear());
+        f();
+        WARNINGS.with(|warnings| warnings.take())
+    }
+
+    #[test]
+    fn default_generation_logs_no_warnings() {
+        let dir = std::env::temp_dir().join("ciphercanvas-quiet-test");
+        let options = QrCodeOptions {
+            output_path: Some(dir.join("wifi.png")),
+            formats: vec!["png".to_string(), "svg".to_string()],
+            write: WriteOptions {
+                dry_run: true,
+                ..wifi_options().write
+            },
+            ..wifi_options()
+        };
+        let modules = {
+            let (qrcode, _) = encode_qr_code(&options).unwrap();
+            modules_with_quiet_zone(&qrcode)
+        };
+        assert_ne!(options.size % modules, 0);
+        let warnings = warnings_from(|| {
+            generate_qr_code(&options).unwrap();
+        });
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[test]
+    fn oversized_images_are_an_error() {
+        let options = QrCodeOptions {
+            scale: Some(u32::MAX),
+            ..wifi_options()
+        };
+        assert!(matches!(encode_qr_code(&options), Err(Error::Image(_))));
+
+        for snap in [false, true] {
+            let options = QrCodeOptions {
+                size: u32::MAX,
+                snap,
+                ..wifi_options()
+            };
+            assert!(matches!(encode_qr_code(&options), Err(Error::Image(_))));
+        }
+    }
+
+    #[test]
+    fn scale_sets_whole_pixels_per_module() {
+        let options = QrCodeOptions {
+            scale: Some(3),
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let size = image_size(&qrcode, &options);
+        assert_eq!(size, (qrcode.width() as u32 + 8) * 3);
+        assert!(
+            render_svg(&qrcode, &options, &options.dark_color)
+                .contains(&format!(r#"width="{size}""#))
+        );
+    }
+
+    #[test]
+    fn direct_rasterization_matches_svg() {
+        let (qrcode, _) = encode_qr_code(&wifi_options()).unwrap();
+        let sizes = (100..=600).step_by(13).map(|size| (size, None, "#ffffff"));
+        let styled = [(300, None, "none"), (0, Some(3), "#fafad2")];
+        for (size, scale, light) in sizes.chain(styled) {
+            // "none" is only set internally for background images, so it's never validated.
+            let options = QrCodeOptions {
+                size,
+                scale,
+                dark_color: "#1a237e".to_string(),
+                light_color: light.to_string(),
+                ..wifi_options()
+            };
+            let (document, width) = render_document(&qrcode, &options, &options.dark_color);
+            let via_svg = svg_to_rgba_image(document.as_bytes(), width).unwrap();
+            let direct = render_raster(&qrcode, &options, &options.dark_color);
+            assert_eq!(direct.dimensions(), via_svg.dimensions());
+            assert!(
+                direct == via_svg,
+                "pixels differ at size {size}, scale {scale:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn extra_outputs_are_written_from_one_generation() {
+        let dir = std::env::temp_dir().join("ciphercanvas-extra-outputs-test");
+        let options = QrCodeOptions {
+            output_path: Some(dir.join("wifi.svg")),
+            extra_outputs: vec![
+                (dir.join("wifi.png"), "png".to_string()),
+                (dir.join("print.eps"), "eps".to_string()),
+            ],
+            write: WriteOptions {
+                dry_run: true,
+                ..wifi_options().write
+            },
+            ..wifi_options()
+        };
+        let metadata = generate_qr_code(&options).unwrap();
+        let files: Vec<_> = metadata
+            .files
+            .iter()
+            .map(|file| (file.path.clone(), file.format.as_str()))
+            .collect();
+        assert_eq!(
+            files,
+            [
+                (dir.join("wifi.svg"), "svg"),
+                (dir.join("wifi.png"), "png"),
+                (dir.join("print.eps"), "eps"),
+            ]
+        );
+    }
+
+    #[test]
+    fn aztec_codes_share_the_output_pipeline() {
+        let dir = std::env::temp_dir().join("ciphercanvas-aztec-test");
+        let options = QrCodeOptions {
+            symbology: Symbology::Aztec,
+            output_path: Some(dir.join("ticket.png")),
+            label: Some("Gate 4".to_string()),
+            write: WriteOptions {
+                dry_run: true,
+                ..wifi_options().write
+            },
+            ..wifi_options()
+        };
+        let metadata = generate_qr_code(&options).unwrap();
+        assert_eq!(metadata.symbology, Symbology::Aztec);
+        assert_eq!(metadata.mask, None);
+        assert_eq!(metadata.ec_level, "50%");
+        assert_eq!(metadata.files[0].path, dir.join("ticket.png"));
+        assert!(metadata.files[0].bytes > 0);
+    }
+
+    #[test]
+    fn invert_mirrors_default_colors() {
+        let default = QrCodeOptions {
+            scale: Some(4),
+            ..wifi_options()
+        };
+        let mut inverted = default.clone();
+        inverted.invert_colors();
+
+        let (qrcode, _) = encode_qr_code(&default).unwrap();
+        let render = |options: &QrCodeOptions| {
+            let (svg, width) = render_document(&qrcode, options, &options.dark_color);
+            svg_to_rgba_image(svg.as_bytes(), width).unwrap()
+        };
+        let (default, inverted) = (render(&default), render(&inverted));
+        assert_eq!(default.dimensions(), inverted.dimensions());
+        for (a, b) in default.pixels().zip(inverted.pixels()) {
+            assert_eq!([a[0], a[1], a[2]], [255 - b[0], 255 - b[1], 255 - b[2]]);
+        }
+    }
+
+    #[test]
+    fn malformed_colors_fail_before_rendering() {
+        for color in ["#12", "#gggggg", "#+12345", "notacolor"] {
+            let options = QrCodeOptions {
+                dark_color: color.to_string(),
+                ..wifi_options()
+            };
+            assert!(matches!(
+                encode_qr_code(&options),
+                Err(Error::InvalidColor(_))
+            ));
+        }
+        let options = QrCodeOptions {
+            gradient: Some(("#000000".to_string(), "#12345".to_string())),
+            ..wifi_options()
+        };
+        assert!(matches!(
+            options.validate_colors(),
+            Err(Error::InvalidColor(_))
+        ));
+        assert!(wifi_options().validate_colors().is_ok());
+    }
+
+    #[test]
+    fn styled_svg_output_is_byte_identical_across_runs() {
+        let dir = std::env::temp_dir().join("ciphercanvas-deterministic-test");
+        let options = QrCodeOptions {
+            output_path: Some(dir.join("wifi.svg")),
+            formats: vec!["svg".to_string()],
+            module_shape: ModuleShape::Rounded,
+            eye_color: Some("#cc0000".to_string()),
+            gradient: Some(("#0044cc".to_string(), "#cc0044".to_string())),
+            gradient_angle: 45.0,
+            label: Some("MyNetwork".to_string()),
+            frame_width: Some(8),
+            physical_size: Some(1.5),
+            write: WriteOptions {
+                overwrite: true,
+                create_dirs: true,
+                ..WriteOptions::default()
+            },
+            ..wifi_options()
+        };
+
+        let mut runs = (0..2).map(|_| {
+            let metadata = generate_qr_code(&options).unwrap();
+            std::fs::read(&metadata.files[0].path).unwrap()
+        });
+        let (first, second) = (runs.next().unwrap(), runs.next().unwrap());
+        assert_eq!(first, second);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn dots_keep_finder_patterns_square() {
+        let options = QrCodeOptions {
+            module_shape: ModuleShape::Dots,
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let svg = render_shaped_svg(&qrcode, &options, "#000000");
+        let square = render_svg(&qrcode, &wifi_options(), "#000000");
+        assert_eq!(
+            svg_doc::root_attribute(&svg, "viewBox"),
+            svg_doc::root_attribute(&square, "viewBox")
+        );
+        assert!(svg.contains("<circle "));
+
+        let finder_modules = (0..qrcode.width())
+            .flat_map(|y| (0..qrcode.width()).map(move |x| (x, y)))
+            .filter(|&(x, y)| finder_part(&qrcode, x, y).is_some() && qrcode[(x, y)] == Color::Dark)
+            .count();
+        assert_eq!(finder_modules, 3 * (24 + 9));
+        assert_eq!(
+            svg.matches("<circle ").count() + finder_modules,
+            qrcode
+                .to_colors()
+                .iter()
+                .filter(|c| **c == Color::Dark)
+                .count()
+        );
+    }
+
+    #[test]
+    fn eye_colors_paint_finder_patterns() {
+        let options = QrCodeOptions {
+            eye_color: Some("#ff0000".to_string()),
+            eye_ball_color: Some("#0000ff".to_string()),
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let svg = render_svg(&qrcode, &options, "#000000");
+        assert!(svg.contains(r##"<path fill="#ff0000""##));
+        assert!(svg.contains(r##"<path fill="#0000ff""##));
+
+        let width = qrcode.width();
+        assert_eq!(finder_part(&qrcode, 0, 0), Some(FinderPart::Frame));
+        assert_eq!(finder_part(&qrcode, 3, 3), Some(FinderPart::Ball));
+        assert_eq!(finder_part(&qrcode, width - 4, 3), Some(FinderPart::Ball));
+        assert_eq!(finder_part(&qrcode, 3, width - 1), Some(FinderPart::Frame));
+        assert_eq!(finder_part(&qrcode, width - 1, width - 1), None);
+        assert_eq!(finder_part(&qrcode, 7, 0), None);
+    }
+
+    #[test]
+    fn styled_eyes_still_decode() {
+        for eye_style in [EyeStyle::Rounded, EyeStyle::Circle] {
+            let options = QrCodeOptions {
+                eye_style,
+                eye_color: Some("#cc0000".to_string()),
+                ..wifi_options()
+            };
+            let (qrcode, _) = encode_qr_code(&options).unwrap();
+            let svg = render_svg(&qrcode, &options, "#000000");
+            assert!(svg.contains(r##"<path fill="#cc0000" fill-rule="evenodd""##));
+            assert_eq!(svg.matches('Z').count(), 9);
+
+            assert!(verify_qr_code(&options).is_ok());
+        }
+    }
+
+    #[test]
+    fn gradient_fills_data_modules_only() {
+        let options = QrCodeOptions {
+            gradient: Some(("#ff0000".to_string(), "#0000ff".to_string())),
+            gradient_angle: 90.0,
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let svg = render_svg(&qrcode, &options, "#000000");
+        let size = image_size(&qrcode, &options).div_ceil(modules_with_quiet_zone(&qrcode))
+            * modules_with_quiet_zone(&qrcode);
+        let center = f64::from(size) / 2.0;
+        assert!(svg.contains(&format!(
+            r#"x1="{center:.2}" y1="0.00" x2="{center:.2}" y2="{size}.00""#
+        )));
+        assert!(svg.contains(r#"<g fill="url(#foreground-gradient)">"#));
+        assert!(svg.contains(r##"<path fill="#000000""##));
+
+        let options = QrCodeOptions {
+            gradient_eyes: true,
+            ..options
+        };
+        let svg = render_svg(&qrcode, &options, "#000000");
+        assert!(!svg.contains(r##"<path fill="#000000""##));
+    }
+
+    #[test]
+    fn contrast_ratio_matches_wcag() {
+        assert!((contrast_ratio("#000000", "#ffffff") - 21.0).abs() < 1e-9);
+        assert!((contrast_ratio("#777777", "#777777") - 1.0).abs() < 1e-9);
+        assert!(contrast_ratio("#ffff00", "#ffffff") < MIN_CONTRAST_RATIO);
+        assert!(contrast_ratio("#1a237e", "#ffffff") > MIN_CONTRAST_RATIO);
+    }
+
+    #[test]
+    fn eps_bounding_box_follows_physical_size() {
+        let options = QrCodeOptions {
+            physical_size: Some(2.0),
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let eps = render_eps(&qrcode, &options);
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 144 144\n"));
+        assert!(eps.contains("0.0000 0.0000 0.0000 setrgbcolor"));
+        // The top row of the code starts with the top-left finder pattern, seven modules wide.
+        let top_row = modules_with_quiet_zone(&qrcode) - 5;
+        assert!(eps.contains(&format!("\n4 {top_row} 7 1 rectfill\n")));
+        assert!(eps.trim_end().ends_with("showpage\n%%EOF"));
+    }
+
+    #[test]
+    fn micro_used_when_payload_fits() {
+        let qrcode = encode_micro(b"12345").unwrap();
+        assert_eq!(qrcode.version(), Version::Micro(1));
+        assert_eq!(modules_with_quiet_zone(&qrcode), 11 + 4);
+    }
+
+    #[test]
+    fn micro_falls_back_to_regular_code() {
+        let options = QrCodeOptions {
+            micro: true,
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        assert!(!qrcode.version().is_micro());
+    }
+
+    #[test]
+    fn forced_version_is_used() {
+        let options = QrCodeOptions {
+            version: Some(10),
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        assert_eq!(qrcode.version(), Version::Normal(10));
+    }
+
+    #[test]
+    fn format_info_matches_encoder_choices() {
+        // Error correction indicators of ISO/IEC 18004, table 12.
+        for (ec_level, indicator) in [
+            (EcLevel::L, 0b01),
+            (EcLevel::M, 0b00),
+            (EcLevel::Q, 0b11),
+            (EcLevel::H, 0b10),
+        ] {
+            let qrcode = QrCode::with_error_correction_level(b"ciphercanvas", ec_level).unwrap();
+            assert_eq!(format_data(&qrcode) >> 3, indicator);
+            assert!(mask_pattern(&qrcode) < 8);
+        }
+
+        let micro = QrCode::with_version(b"123", Version::Micro(1), EcLevel::L).unwrap();
+        assert_eq!(format_data(&micro) >> 2, 0, "M1 is symbol number 0");
+        assert!(mask_pattern(&micro) < 4);
+    }
+
+    #[test]
+    fn numeric_payloads_use_numeric_mode() {
+        // Version 1-L holds 41 digits in numeric mode but only 17 bytes in byte mode.
+        let options = QrCodeOptions {
+            payload: Payload::Text {
+                data: "4".repeat(41),
+            },
+            ec_level: ErrorCorrection::L,
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        assert_eq!(qrcode.version(), Version::Normal(1));
+    }
+
+    #[test]
+    fn auto_ec_level_keeps_the_smallest_version() {
+        let options = QrCodeOptions {
+            ec_level: ErrorCorrection::Auto,
+            ..wifi_options()
+        };
+        let (qrcode, payload) = encode_qr_code(&options).unwrap();
+        let smallest = QrCode::with_error_correction_level(&payload, EcLevel::L).unwrap();
+        assert_eq!(qrcode.version(), smallest.version());
+        let stronger = [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L]
+            .into_iter()
+            .take_while(|&level| level != qrcode.error_correction_level());
+        for level in stronger {
+            assert!(QrCode::with_version(&payload, qrcode.version(), level).is_err());
+        }
+
+        let (high, _) = encode_qr_code(&wifi_options()).unwrap();
+        assert!(qrcode.width() < high.width());
     }
 
     #[test]
-    fn wifi_qr_empty_ssid() {
-        let payload = build_wifi_qr_payload("", "WPA", "password");
-        assert_eq!(payload, "WIFI:S:;T:WPA;P:password;;");
+    fn forced_version_too_small_reports_minimum() {
+        let options = QrCodeOptions {
+            version: Some(1),
+            ..wifi_options()
+        };
+        let Err(Error::PayloadTooLarge { requested, minimum }) = encode_qr_code(&options) else {
+            panic!("expected PayloadTooLarge");
+        };
+        assert_eq!(requested, 1);
+        assert!(minimum > 1);
     }
 
     #[test]
-    fn wifi_qr_empty_password() {
-        let payload = build_wifi_qr_payload("MyNetwork", "None", "");
-        assert_eq!(payload, "WIFI:S:MyNetwork;T:NONE;P:;;");
+    fn snap_rounds_size_up_to_module_multiple() {
+        let options = QrCodeOptions {
+            size: 500,
+            snap: true,
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let modules = qrcode.width() as u32 + 8;
+        let size = image_size(&qrcode, &options);
+        assert!(size.is_multiple_of(modules));
+        assert!(size >= 500 && size < 500 + modules);
     }
 
     #[test]
-    fn wifi_qr_special_chars_in_ssid() {
-        let payload = build_wifi_qr_payload("My\\Network", "WPA", "pass\\word");
-        assert_eq!(payload, "WIFI:S:My\\\\Network;T:WPA;P:pass\\\\word;;");
+    fn print_resolution_from_width() {
+        let options = QrCodeOptions {
+            scale: Some(10),
+            print_width_mm: Some(25.4),
+            ..wifi_options()
+        };
+        let (qrcode, _) = encode_qr_code(&options).unwrap();
+        let modules = f64::from(qrcode.width() as u32 + 8);
+        let print = print_resolution(
+            modules_with_quiet_zone(&qrcode),
+            image_size(&qrcode, &options),
+            &options,
+        )
+        .unwrap();
+        assert!((print.dpi - modules * 10.0).abs() < 1e-9);
+        assert!((print.module_size_mm - 25.4 / modules).abs() < 1e-9);
     }
 }
@@ -0,0 +1,191 @@
+use std::ops::Range;
+
+/// Font family used for text drawn into the QR SVG. It is bundled with the binary so raster
+/// output renders the same everywhere; SVG viewers without it fall back to `sans-serif`.
+pub(crate) const LABEL_FONT_FAMILY: &str = "Tuffy";
+
+/// Byte range of the root element's start tag, without the closing `>`.
+fn root_tag(svg: &str) -> Option<Range<usize>> {
+    let start = svg.find("<svg")?;
+    let end = start + svg[start..].find('>')?;
+    Some(start..end)
+}
+
+/// Byte range of the value of attribute `name` on the root element.
+fn root_attribute_range(svg: &str, name: &str) -> Option<Range<usize>> {
+    let tag = root_tag(svg)?;
+    let needle = format!(" {name}=\"");
+    let value_start = tag.start + svg[tag.clone()].find(&needle)? + needle.len();
+    let value_end = value_start + svg[value_start..tag.end].find('"')?;
+    Some(value_start..value_end)
+}
+
+/// Value of attribute `name` on the root `<svg>` element.
+pub(crate) fn root_attribute<'a>(svg: &'a str, name: &str) -> Option<&'a str> {
+    root_attribute_range(svg, name).map(|range| &svg[range])
+}
+
+/// Replace the value of attribute `name` on the root `<svg>` element, if present.
+fn set_root_attribute(svg: &mut String, name: &str, value: &str) {
+    if let Some(range) = root_attribute_range(svg, name) {
+        svg.replace_range(range, value);
+    }
+}
+
+/// The root element's `viewBox` as `(min_x, min_y, width, height)`.
+fn view_box(svg: &str) -> Option<(f64, f64, f64, f64)> {
+    let values: Vec<f64> = root_attribute(svg, "viewBox")?
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .ok()?;
+    match values[..] {
+        [x, y, width, height] => Some((x, y, width, height)),
+        _ => None,
+    }
+}
+
+/// Width of the root element's `viewBox`, in user units.
+pub(crate) fn view_box_width(svg: &str) -> Option<f64> {
+    view_box(svg).map(|(_, _, width, _)| width)
+}
+
+/// Draw a solid border of `width` user units around the whole drawing, growing the canvas
+/// outward so nothing inside (such as the quiet zone) is covered.
+pub(crate) fn add_frame(svg: &str, width: u32, color: &str) -> String {
+    let Some((x, y, w, h)) = view_box(svg) else {
+        return svg.to_string();
+    };
+    let frame = f64::from(width);
+    let (x, y, w, h) = (x - frame, y - frame, w + 2.0 * frame, h + 2.0 * frame);
+
+    let mut svg = svg.to_string();
+    set_root_attribute(&mut svg, "width", &w.to_string());
+    set_root_attribute(&mut svg, "height", &h.to_string());
+    set_root_attribute(&mut svg, "viewBox", &format!("{x} {y} {w} {h}"));
+
+    let element = format!(r#"<rect x="{x}" y="{y}" width="{w}" height="{h}" fill="{color}"/>"#);
+    if let Some(tag) = root_tag(&svg) {
+        svg.insert_str(tag.end + 1, &element);
+    }
+    svg
+}
+
+/// Replace the pixel `width`/`height` of the root `<svg>` element with physical dimensions in
+/// inches, `inches` wide and as tall as the `viewBox` aspect ratio requires.
+///
+/// The `viewBox` is left untouched, so the drawing scales to the physical size in any viewer.
+pub(crate) fn set_physical_size(svg: &str, inches: f64) -> String {
+    let height = view_box(svg).map_or(inches, |(_, _, w, h)| inches * h / w);
+
+    let mut svg = svg.to_string();
+    set_root_attribute(&mut svg, "width", &format!("{inches}in"));
+    set_root_attribute(&mut svg, "height", &format!("{height}in"));
+    svg
+}
+
+/// Append a centered line of text below the drawing, growing the canvas to make room for it.
+///
+/// The extra strip is filled with `background` so the label sits on the same color as the quiet
+/// zone, and the text is drawn in `color`. It uses `font_family` when given, falling back to the
+/// bundled font.
+pub(crate) fn add_label(
+    svg: &str,
+    text: &str,
+    font_size: u32,
+    font_family: Option<&str>,
+    color: &str,
+    background: &str,
+) -> String {
+    let Some((x, y, width, height)) = view_box(svg) else {
+        return svg.to_string();
+    };
+    let font_size = f64::from(font_size);
+    let strip = (font_size * 1.5).ceil();
+    let new_height = height + strip;
+
+    let mut svg = svg.to_string();
+    set_root_attribute(&mut svg, "height", &new_height.to_string());
+    set_root_attribute(
+        &mut svg,
+        "viewBox",
+        &format!("{x} {y} {width} {new_height}"),
+    );
+
+    let element = format!(
+        r#"<rect x="{x}" y="{strip_y}" width="{width}" height="{strip}" fill="{background}"/><text x="{center}" y="{baseline}" font-family="{families}" font-size="{font_size}" text-anchor="middle" fill="{color}">{text}</text>"#,
+        strip_y = y + height,
+        center = x + width / 2.0,
+        baseline = y + height + strip - (strip - font_size) / 2.0 - font_size * 0.2,
+        text = escape_xml(text),
+        families = match font_family {
+            Some(family) => format!("'{}', {LABEL_FONT_FAMILY}, sans-serif", escape_xml(family)),
+            None => format!("{LABEL_FONT_FAMILY}, sans-serif"),
+        },
+    );
+    match svg.rfind("</svg>") {
+        Some(end) => svg.insert_str(end, &element),
+        None => svg.push_str(&element),
+    }
+    svg
+}
+
+/// Escape text for use in XML character data and attribute values.
+pub(crate) fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SVG: &str = r#"<?xml version="1.0"?><svg width="533" height="533" viewBox="0 0 533 533"><rect width="533" height="533"/></svg>"#;
+
+    #[test]
+    fn physical_size_replaces_root_dimensions() {
+        assert_eq!(
+            set_physical_size(SVG, 1.5),
+            r#"<?xml version="1.0"?><svg width="1.5in" height="1.5in" viewBox="0 0 533 533"><rect width="533" height="533"/></svg>"#
+        );
+    }
+
+    #[test]
+    fn label_grows_canvas_and_escapes_text() {
+        let svg = add_label(SVG, "Guest & <Friends>", 20, None, "#000000", "#ffffff");
+        assert_eq!(root_attribute(&svg, "width"), Some("533"));
+        assert_eq!(root_attribute(&svg, "height"), Some("563"));
+        assert_eq!(root_attribute(&svg, "viewBox"), Some("0 0 533 563"));
+        assert!(svg.contains(">Guest &amp; &lt;Friends&gt;</text></svg>"));
+    }
+
+    #[test]
+    fn frame_surrounds_drawing() {
+        let svg = add_frame(SVG, 10, "#ff0000");
+        assert_eq!(root_attribute(&svg, "width"), Some("553"));
+        assert_eq!(root_attribute(&svg, "viewBox"), Some("-10 -10 553 553"));
+        assert!(svg.contains(
+            r##"viewBox="-10 -10 553 553"><rect x="-10" y="-10" width="553" height="553" fill="#ff0000"/><rect width="533""##
+        ));
+    }
+
+    #[test]
+    fn physical_size_keeps_label_aspect_ratio() {
+        let svg = set_physical_size(&add_label(SVG, "x", 20, None, "#000", "#fff"), 2.0);
+        let height: f64 = root_attribute(&svg, "height")
+            .and_then(|h| h.strip_suffix("in"))
+            .and_then(|h| h.parse().ok())
+            .unwrap();
+        assert!((height - 2.0 * 563.0 / 533.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,172 @@
+use clap::ValueEnum;
+use log::info;
+#[cfg(feature = "kitty_graphics")]
+use log::warn;
+use std::io::{self, IsTerminal};
+
+/// Ways to show the QR code in the terminal instead of writing a file.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum TerminalMode {
+    /// Pick the best mode the current terminal supports.
+    Auto,
+    /// The Kitty graphics protocol.
+    #[cfg(feature = "kitty_graphics")]
+    Kitty,
+    /// The iTerm2 inline image protocol.
+    #[cfg(feature = "iterm2_graphics")]
+    Iterm2,
+    /// Unicode half-block characters, two modules per character cell.
+    Unicode,
+    /// Plain `#` and space characters, for logs and plain-text documents.
+    Ascii,
+}
+
+impl TerminalMode {
+    /// Resolve [`TerminalMode::Auto`] to a concrete mode for the current environment.
+    ///
+    /// An explicit Kitty mode falls back to text output when the terminal doesn't look like it
+    /// supports the protocol. `force_kitty` skips that check and makes Auto pick Kitty. Any other
+    /// mode is returned unchanged.
+    pub(crate) fn resolve(self, force_kitty: bool) -> Self {
+        let env = |name: &str| std::env::var(name).ok();
+        let mode = choose(self, env, io::stdout().is_terminal(), force_kitty);
+        if self == Self::Auto {
+            info!("Detected terminal output mode: {mode:?}");
+        }
+        mode
+    }
+}
+
+/// Resolve `mode` for the terminal described by `env`; see [`TerminalMode::resolve`].
+#[cfg_attr(not(feature = "kitty_graphics"), allow(unused_variables))]
+fn choose(
+    mode: TerminalMode,
+    env: impl Fn(&str) -> Option<String>,
+    stdout_is_terminal: bool,
+    force_kitty: bool,
+) -> TerminalMode {
+    match mode {
+        #[cfg(feature = "kitty_graphics")]
+        TerminalMode::Auto if force_kitty => TerminalMode::Kitty,
+        TerminalMode::Auto => detect(env, stdout_is_terminal),
+        #[cfg(feature = "kitty_graphics")]
+        TerminalMode::Kitty if !force_kitty && !supports_kitty(&env) => {
+            let fallback = text_mode(&env, stdout_is_terminal);
+            warn!(
+                "This terminal doesn't appear to support Kitty graphics, so the code is shown with \
+                 {fallback:?} output instead. Use --force-kitty to send Kitty graphics anyway."
+            );
+            fallback
+        }
+        mode => mode,
+    }
+}
+
+/// Choose a terminal mode from environment variables, preferring Kitty, then iTerm2, then
+/// Unicode blocks, then ASCII.
+///
+/// Only protocols compiled into this build are considered. When stdout isn't a terminal, the
+/// output is most likely going to a file or pipe, so plain ASCII is used.
+fn detect(env: impl Fn(&str) -> Option<String>, stdout_is_terminal: bool) -> TerminalMode {
+    if !stdout_is_terminal {
+        return TerminalMode::Ascii;
+    }
+
+    #[cfg(feature = "kitty_graphics")]
+    if supports_kitty(&env) {
+        return TerminalMode::Kitty;
+    }
+
+    #[cfg(feature = "iterm2_graphics")]
+    if env("TERM_PROGRAM").as_deref() == Some("iTerm.app")
+        || env("LC_TERMINAL").as_deref() == Some("iTerm2")
+    {
+        return TerminalMode::Iterm2;
+    }
+
+    text_mode(&env, stdout_is_terminal)
+}
+
+/// Whether the terminal understands the Kitty graphics protocol, judging by its environment.
+///
+/// tmux and screen don't pass the protocol's escape sequences through, so they rule it out even
+/// when running inside Kitty.
+#[cfg(feature = "kitty_graphics")]
+fn supports_kitty(env: &impl Fn(&str) -> Option<String>) -> bool {
+    let term = env("TERM").unwrap_or_default();
+    if env("TMUX").is_some() || term.starts_with("screen") || term.starts_with("tmux") {
+        return false;
+    }
+    env("KITTY_WINDOW_ID").is_some()
+        || term == "xterm-kitty"
+        || term == "xterm-ghostty"
+        || env("TERM_PROGRAM").as_deref() == Some("WezTerm")
+}
+
+/// Unicode blocks, or ASCII for dumb terminals and when stdout isn't a terminal.
+fn text_mode(env: &impl Fn(&str) -> Option<String>, stdout_is_terminal: bool) -> TerminalMode {
+    if !stdout_is_terminal || env("TERM").as_deref() == Some("dumb") {
+        return TerminalMode::Ascii;
+    }
+    TerminalMode::Unicode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env_from<'a>(vars: &'a [(&'a str, &'a str)]) -> impl Fn(&str) -> Option<String> + 'a {
+        move |name| {
+            vars.iter()
+                .find(|(key, _)| *key == name)
+                .map(|(_, value)| value.to_string())
+        }
+    }
+
+    #[test]
+    fn falls_back_to_unicode() {
+        let mode = detect(env_from(&[("TERM", "xterm-256color")]), true);
+        assert_eq!(mode, TerminalMode::Unicode);
+    }
+
+    #[test]
+    fn dumb_terminal_uses_ascii() {
+        let mode = detect(env_from(&[("TERM", "dumb")]), true);
+        assert_eq!(mode, TerminalMode::Ascii);
+    }
+
+    #[test]
+    fn non_terminal_stdout_uses_ascii() {
+        let mode = detect(env_from(&[("KITTY_WINDOW_ID", "1")]), false);
+        assert_eq!(mode, TerminalMode::Ascii);
+    }
+
+    #[cfg(feature = "kitty_graphics")]
+    #[test]
+    fn detects_kitty() {
+        let mode = detect(env_from(&[("KITTY_WINDOW_ID", "1")]), true);
+        assert_eq!(mode, TerminalMode::Kitty);
+    }
+
+    #[cfg(feature = "kitty_graphics")]
+    #[test]
+    fn tmux_hides_kitty() {
+        let env = env_from(&[("KITTY_WINDOW_ID", "1"), ("TERM", "tmux-256color")]);
+        assert_ne!(detect(&env, true), TerminalMode::Kitty);
+        assert_eq!(
+            choose(TerminalMode::Kitty, &env, true, false),
+            TerminalMode::Unicode
+        );
+        assert_eq!(
+            choose(TerminalMode::Kitty, &env, true, true),
+            TerminalMode::Kitty
+        );
+    }
+
+    #[cfg(feature = "iterm2_graphics")]
+    #[test]
+    fn detects_iterm2() {
+        let mode = detect(env_from(&[("TERM_PROGRAM", "iTerm.app")]), true);
+        assert_eq!(mode, TerminalMode::Iterm2);
+    }
+}